@@ -11,5 +11,5 @@ begin
   a[3] := 39;
   a[4] := 38;
 
-  write( a[0] + a[4] );
+  writeln( a[0] + a[4] );
 end.
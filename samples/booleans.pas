@@ -0,0 +1,42 @@
+var done, found : boolean;
+var i : integer;
+var flags : array [1..5] of boolean;
+
+function isEven(n : integer) : boolean;
+begin
+  isEven := (n div 2) * 2 = n;
+end;
+
+procedure toggle(var b : boolean);
+begin
+  b := not b;
+end;
+
+begin
+  done := false;
+  writeln(done);
+  toggle(done);
+  writeln(done);
+  writeln(not done);
+
+  i := 1;
+  while i <= 5 do
+  begin
+    flags[i] := isEven(i);
+    i := i + 1;
+  end;
+
+  found := false;
+  i := 1;
+  repeat
+    if flags[i] then
+    begin
+      found := true;
+      writeln(i);
+    end;
+    i := i + 1;
+  until found or (i > 5);
+
+  writeln(found and not flags[1]);
+  writeln(isEven(7) = flags[3]);
+end.
@@ -10,9 +10,10 @@ begin
   iterator := 0;
   while iterator < len do
   begin
-    write( a[iterator] );
+    write(a[iterator], ' ');
     iterator := iterator + 1;
   end;
+  writeln;
 end;
 
 procedure swap;
@@ -51,10 +52,10 @@ begin
   a[3] := 8;
   a[4] := 14;
 
+  write('Before: ');
   printArray;
   bubbleSort;
 
-  write(888888888);
-
+  write('After:  ');
   printArray;
 end.
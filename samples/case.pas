@@ -0,0 +1,46 @@
+var i : integer;
+var c : char;
+
+procedure describe(n : integer);
+begin
+  case n of
+    1, 3, 5, 7, 9: writeln(n, ' is odd');
+    2, 4, 6, 8: writeln(n, ' is even');
+    0: writeln('zero');
+  else
+    writeln(n, ' is out of range');
+  end;
+end;
+
+function grade(score : integer) : char;
+begin
+  case score of
+    90..100: grade := 'A';
+    80..89: grade := 'B';
+    70..79: grade := 'C';
+    -1000..69: grade := 'F';
+  otherwise
+    grade := '?';
+  end;
+end;
+
+begin
+  for i := -1 to 10 do
+    describe(i);
+
+  writeln(grade(95), grade(85), grade(72), grade(10), grade(101));
+
+  for c := 'a' to 'f' do
+  begin
+    case c of
+      'a', 'e', 'i', 'o', 'u': write('vowel ');
+      'b'..'d': write('consonant ');
+    end;
+  end;
+  writeln;
+
+  case 3 > 2 of
+    true: writeln('three is bigger');
+    false: writeln('two is bigger');
+  end;
+end.
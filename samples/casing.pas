@@ -0,0 +1,40 @@
+{ Keywords and names may be written in any case }
+CONST Limit = 5;
+
+TYPE Point = RECORD
+       X, Y : Integer;
+     END;
+
+VAR Total, i : INTEGER;
+VAR Origin : point;
+var Done : Boolean;
+
+Function Square(N : integer) : Integer;
+Begin
+  square := n * n;
+End;
+
+Procedure Add(VAR Sum : integer; Amount : INTEGER); Forward;
+
+procedure add(var sum : integer; amount : integer);
+begin
+  SUM := Sum + AMOUNT;
+end;
+
+BEGIN
+  total := 0;
+  FOR I := 1 TO limit DO
+    ADD(TOTAL, SQUARE(i));
+  WriteLn('Sum of squares: ', Total);
+
+  origin.x := 3;
+  WITH Origin DO
+    y := X * 2;
+  writeln(ORIGIN.X, ' ', origin.Y);
+
+  done := TRUE;
+  IF Done AND NOT False THEN
+  BEGIN
+    WRITELN('Done');
+  END;
+END.
@@ -0,0 +1,37 @@
+var c : char;
+var i : integer;
+var word : array [1..5] of char;
+
+function upcase(ch : char) : char;
+begin
+  upcase := ch;
+  if (ch >= 'a') and (ch <= 'z') then
+  begin
+    upcase := chr(ord(ch) - 32);
+  end;
+end;
+
+begin
+  c := 'a';
+  writeln(c);
+  writeln(ord(c));
+  writeln(chr(ord(c) + 1));
+  writeln(#65);
+  writeln('''');
+
+  word[1] := 'h';
+  word[2] := 'e';
+  word[3] := 'l';
+  word[4] := 'l';
+  word[5] := 'o';
+
+  i := 1;
+  while i <= 5 do
+  begin
+    writeln(upcase(word[i]));
+    i := i + 1;
+  end;
+
+  writeln(ord(true));
+  writeln('b' > c);
+end.
@@ -0,0 +1,35 @@
+{ This program shows the three kinds of comments,
+  and switches range checking with the R directive. }
+
+type Digit = 0..9;   // A subrange, so stores into it are checked
+
+var d : Digit;
+var total : integer;
+
+(* Comments may span
+   several lines, and { braces } may sit inside these ones *)
+
+procedure count(n : integer);   { n is passed by value }
+var k : integer;
+begin
+  for k := 1 to n do
+    total := total + k;   // Sum 1..n
+end;
+
+begin
+  total := 0;
+  count(4);
+  writeln('Total: ', total (* inline *), {another} ' done');
+
+  // With range checking switched off, a subrange can hold values outside its bounds
+  {$R-}
+  d := 9;
+  d := d + 3;
+  writeln('Unchecked: ', d);
+
+  {$R+}
+  writeln('Checked again, so this stops the program:');
+  d := d + 0;
+  writeln('Not reached');
+end.
+// Comments may also follow the end of the program
@@ -0,0 +1,43 @@
+const N = 5;
+      Max = N * 2 - 1;
+      Half = Max / 2;
+      First = 'A';
+      Last = chr(ord(First) + N - 1);
+      Verbose = Max > 8;
+      Title = 'Squares';
+      MinusOne = -1;
+
+var squares : array [MinusOne..Max] of integer;
+var i : integer;
+var c : char;
+
+procedure show(n : integer);
+const Width = 3;
+begin
+  write(n * Width, ' ');
+end;
+
+begin
+  writeln(Title, ' up to ', Max, ' (half of that is ', Half, ')');
+  for i := MinusOne to Max do
+    squares[i] := i * i;
+  for i := MinusOne to Max do
+    write(squares[i], ' ');
+  writeln;
+
+  for c := First to Last do
+    write(c);
+  writeln;
+
+  if Verbose then
+  begin
+    for i := 1 to N do
+      show(i);
+    writeln;
+  end;
+
+  case 7 of
+    N: writeln('five');
+    N + 1..Max: writeln('six to nine');
+  end;
+end.
@@ -0,0 +1,97 @@
+type Color = (Red, Green, Blue);
+     Day = (Mon, Tue, Wed, Thu, Fri, Sat, Sun);
+     Weekday = Mon..Fri;
+     Digit = 0..9;
+     Letter = 'a'..'z';
+
+const Last = Sun;
+      Weekend = succ(Fri);
+
+var c : Color;
+var d : Day;
+var w : Weekday;
+var n : Digit;
+var l : Letter;
+var hours : array [Weekday] of integer;
+var seen : array [Color] of boolean;
+var tally : array [Digit] of integer;
+var i : integer;
+
+function name(c : Color) : char;
+begin
+  case c of
+    Red : name := 'R';
+    Green : name := 'G';
+    Blue : name := 'B';
+  end;
+end;
+
+function next(d : Day) : Day;
+begin
+  if d = Last then
+  begin
+    next := Mon;
+  end
+  else begin
+    next := succ(d);
+  end;
+end;
+
+procedure bump(var digit : Digit);
+begin
+  if digit = 9 then
+  begin
+    digit := 0;
+  end
+  else begin
+    digit := digit + 1;
+  end;
+end;
+
+begin
+  for c := Red to Blue do
+    write(name(c), ord(c), ' ');
+  writeln;
+  writeln(name(succ(Red)), name(pred(Blue)), ' ', Green < Blue, ' ', ord(Weekend));
+
+  for w := Mon to Fri do
+    hours[w] := 8 - ord(w);
+  i := 0;
+  for w := Fri downto Mon do
+    i := i + hours[w];
+  writeln('Hours: ', i);
+
+  d := Thu;
+  for i := 1 to 5 do
+  begin
+    d := next(d);
+    write(ord(d), ' ');
+  end;
+  writeln;
+  w := d;
+  writeln(ord(w));
+
+  seen[Red] := true;
+  seen[Green] := false;
+  seen[Blue] := seen[Red] and not seen[Green];
+  writeln(seen[Blue]);
+
+  n := 7;
+  for i := 1 to 25 do
+  begin
+    tally[n] := tally[n] + 1;
+    bump(n);
+  end;
+  for i := 0 to 9 do
+    write(tally[i], ' ');
+  writeln;
+
+  l := 'q';
+  l := succ(l);
+  writeln(l, ' ', pred(l));
+
+  writeln('Stepping out of range stops the program:');
+  n := 9;
+  n := n + 1;
+  writeln('Not reached');
+end.
@@ -22,5 +22,5 @@ begin
     counter := counter + 1;
   end;
 
-  write(max);
+  writeln(max);
 end.
@@ -0,0 +1,39 @@
+var i, total : integer;
+var c : char;
+var squares : array [1..5] of integer;
+
+procedure countdown(from : integer);
+var n : integer;
+begin
+  for n := from downto 1 do
+    write(n, ' ');
+  writeln('liftoff');
+end;
+
+begin
+  for i := 1 to 5 do
+    squares[i] := i * i;
+
+  total := 0;
+  for i := 1 to 5 do
+  begin
+    write(squares[i], ' ');
+    total := total + squares[i];
+  end;
+  writeln('sum = ', total);
+  writeln('i after the loop = ', i);
+
+  for c := 'a' to 'e' do
+    write(c);
+  writeln;
+
+  total := 3;
+  for i := 1 to total do
+    total := total + 1;
+  writeln('total = ', total);
+
+  for i := 5 to 1 do
+    writeln('never printed');
+
+  countdown(3);
+end.
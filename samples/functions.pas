@@ -0,0 +1,31 @@
+var x, y : integer;
+
+function max(a, b : integer) : integer;
+begin
+  if a > b then
+  begin
+    max := a;
+  end
+  else begin
+    max := b;
+  end;
+end;
+
+function square(n : integer) : integer;
+begin
+  Result := n * n;
+end;
+
+function seven : integer;
+begin
+  seven := 7;
+end;
+
+begin
+  x := 3;
+  y := max(x, 5);
+  writeln(y);
+  writeln(square(x) + 1);
+  writeln(max(square(2), seven) * 10);
+  writeln(square(max(x, seven)));
+end.
@@ -0,0 +1,47 @@
+label 10, 20, 99;
+var i, j : integer;
+
+function firstDivisor(n : integer) : integer;
+label 1;
+var d : integer;
+begin
+  firstDivisor := n;
+  for d := 2 to n - 1 do
+  begin
+    if (n div d) * d = n then
+    begin
+      firstDivisor := d;
+      goto 1;
+    end;
+  end;
+1: ;
+end;
+
+begin
+  i := 0;
+10:
+  i := i + 1;
+  write(i, ' ');
+  if i < 5 then
+  begin
+    goto 10;
+  end;
+  writeln;
+
+  goto 20;
+  writeln('skipped');
+20: writeln('jumped forward');
+
+  for i := 1 to 3 do
+    for j := 1 to 3 do
+    begin
+      if i * j = 4 then
+      begin
+        writeln('found ', i, ' * ', j, ' = 4');
+        goto 99;
+      end;
+    end;
+  writeln('not found');
+
+99: writeln(firstDivisor(91), ' ', firstDivisor(13));
+end.
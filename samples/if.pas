@@ -6,14 +6,14 @@ begin
 
   if x = 10 then
   begin
-    write(x);
+    writeln(x);
   end;
 
   if y < 3 then
   begin
-    write(x);
+    writeln(x);
   end
   else begin
-    write(y);
+    writeln(y);
   end;
 end.
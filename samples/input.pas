@@ -0,0 +1,32 @@
+{ Reads a title and then lines of numbers from standard input, writing the total of each line.
+  Run it as: cargo run samples/input.pas < samples/input.txt }
+program Totals(input, output);
+
+type Title = array [1..12] of char;
+
+var heading : Title;
+var n, sum, lines, i : integer;
+var first : char;
+
+begin
+  readln(heading);
+  write('[');
+  for i := 1 to 12 do
+    write(heading[i]);
+  writeln(']');
+
+  lines := 0;
+  while not eof do
+  begin
+    read(first);
+    sum := 0;
+    while not eoln do
+    begin
+      read(n);
+      sum := sum + n;
+    end;
+    readln;
+    lines := lines + 1;
+    writeln('Line ', lines, ' (', first, '): ', sum);
+  end;
+end.
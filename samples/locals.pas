@@ -0,0 +1,50 @@
+var x, count : integer;
+var a : array [0..2] of integer;
+
+procedure swap(var p, q : integer);
+var temp : integer;
+begin
+  temp := p;
+  p := q;
+  q := temp;
+end;
+
+procedure counter;
+var x, calls : integer;
+begin
+  calls := calls + 1;
+  x := 100;
+  count := count + calls;
+end;
+
+function sum(n : integer) : integer;
+var i : integer;
+    squares : array [1..3] of integer;
+begin
+  i := 1;
+  while i <= 3 do
+  begin
+    squares[i] := i * n;
+    i := i + 1;
+  end;
+  sum := squares[1] + squares[2] + squares[3];
+end;
+
+begin
+  x := 7;
+  count := 0;
+
+  counter;
+  counter;
+  counter;
+  writeln(x);
+  writeln(count);
+
+  a[0] := 1;
+  a[2] := 3;
+  swap(a[0], a[2]);
+  writeln(a[0]);
+  writeln(a[2]);
+
+  writeln(sum(2));
+end.
@@ -6,12 +6,12 @@ begin
   repeat
     x := x - 1;
     y := y + 1;
-    write(x);
-  until x <> 4;
+    writeln(x);
+  until x = 4;
 
   while y <> 5 do
     begin
       y := y - 1;
-      write(y);
+      writeln(y);
     end;
 end.
@@ -0,0 +1,64 @@
+const Rows = 3;
+      Cols = 4;
+
+type Matrix = array [1..Rows, 1..Cols] of integer;
+     Grid = array [0..2] of array ['a'..'c'] of char;
+     Cube = array [1..2, 1..2, 1..2] of real;
+
+var a, b : Matrix;
+var g : Grid;
+var c : Cube;
+var i, j, k : integer;
+var ch : char;
+
+procedure show(m : Matrix);
+var r, s : integer;
+begin
+  for r := 1 to Rows do
+  begin
+    for s := 1 to Cols do
+      write(m[r, s], ' ');
+    writeln;
+  end;
+end;
+
+procedure transpose(var m : Matrix);
+var r, s, t : integer;
+begin
+  for r := 1 to Rows do
+    for s := r + 1 to Rows do
+    begin
+      t := m[r][s];
+      m[r][s] := m[s, r];
+      m[s, r] := t;
+    end;
+end;
+
+begin
+  for i := 1 to Rows do
+    for j := 1 to Cols do
+      a[i, j] := i * 10 + j;
+  show(a);
+
+  b := a;
+  transpose(b);
+  b[1][Cols] := 0;
+  show(b);
+  writeln(a[2, 3], ' ', a[2][3], ' ', b[2, 3]);
+
+  for i := 0 to 2 do
+    for ch := 'a' to 'c' do
+      g[i, ch] := chr(ord(ch) + i);
+  for i := 0 to 2 do
+  begin
+    for ch := 'a' to 'c' do
+      write(g[i][ch]);
+    writeln;
+  end;
+
+  for i := 1 to 2 do
+    for j := 1 to 2 do
+      for k := 1 to 2 do
+        c[i, j, k] := i + j / 10 + k / 100;
+  writeln(c[2, 1, 2], ' ', c[1][2][1], ' ', c[2, 2][2]);
+end.
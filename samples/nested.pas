@@ -0,0 +1,66 @@
+var total : integer;
+
+procedure outer(n : integer);
+var count : integer;
+
+  procedure bump(by : integer);
+
+    procedure tally;
+    begin
+      total := total + count;
+    end;
+
+  begin
+    count := count + by;
+    tally;
+  end;
+
+begin
+  count := n;
+  bump(1);
+  bump(10);
+  writeln(count);
+end;
+
+function sumTo(n : integer) : integer;
+var i, acc : integer;
+
+  procedure add;
+  begin
+    acc := acc + i;
+    sumTo := acc;
+  end;
+
+begin
+  i := 1;
+  while i <= n do
+  begin
+    add;
+    i := i + 1;
+  end;
+end;
+
+function depth(n : integer) : integer;
+
+  function deeper : integer;
+  begin
+    deeper := depth(n - 1) + 1;
+  end;
+
+begin
+  if n = 0 then
+  begin
+    depth := 0;
+  end
+  else begin
+    depth := deeper;
+  end;
+end;
+
+begin
+  total := 0;
+  outer(5);
+  writeln(total);
+  writeln(sumTo(4));
+  writeln(depth(6));
+end.
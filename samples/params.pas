@@ -0,0 +1,38 @@
+var x, y, temp : integer;
+var a : array [0..2] of integer;
+
+procedure swap(var p, q : integer);
+begin
+  temp := p;
+  p := q;
+  q := temp;
+end;
+
+procedure show(n : integer; var total : integer);
+begin
+  writeln(n);
+  total := total + n;
+  n := 0;
+end;
+
+begin
+  x := 1;
+  y := 2;
+  swap(x, y);
+  writeln(x);
+  writeln(y);
+
+  a[0] := 10;
+  a[1] := 20;
+  a[2] := 30;
+  swap(a[0], a[2]);
+  writeln(a[0]);
+  writeln(a[2]);
+
+  x := 5;
+  y := 0;
+  show(x * 2, y);
+  show(x, y);
+  writeln(x);
+  writeln(y);
+end.
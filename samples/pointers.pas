@@ -0,0 +1,127 @@
+type List = ^Node;
+     Node = record
+       value : integer;
+       next : List;
+     end;
+     Tree = ^Leaf;
+     Leaf = record
+       key : char;
+       left, right : Tree;
+     end;
+
+var numbers, p : List;
+var root : Tree;
+var counter : ^integer;
+var i : integer;
+
+procedure push(var head : List; v : integer);
+var node : List;
+begin
+  new(node);
+  node^.value := v;
+  node^.next := head;
+  head := node;
+end;
+
+procedure show(head : List);
+begin
+  while head <> nil do
+  begin
+    write(head^.value, ' ');
+    head := head^.next;
+  end;
+  writeln;
+end;
+
+function reverse(head : List) : List;
+var done, rest : List;
+begin
+  done := nil;
+  while head <> nil do
+  begin
+    rest := head^.next;
+    head^.next := done;
+    done := head;
+    head := rest;
+  end;
+  reverse := done;
+end;
+
+procedure insert(var t : Tree; k : char);
+begin
+  if t = nil then
+  begin
+    new(t);
+    t^.key := k;
+    t^.left := nil;
+    t^.right := nil;
+  end
+  else begin
+    if k < t^.key then
+    begin
+      insert(t^.left, k);
+    end
+    else begin
+      insert(t^.right, k);
+    end;
+  end;
+end;
+
+procedure inorder(t : Tree);
+begin
+  if t <> nil then
+  begin
+    inorder(t^.left);
+    write(t^.key);
+    inorder(t^.right);
+  end;
+end;
+
+procedure free(t : Tree);
+begin
+  if t <> nil then
+  begin
+    free(t^.left);
+    free(t^.right);
+    dispose(t);
+  end;
+end;
+
+begin
+  numbers := nil;
+  for i := 1 to 5 do
+    push(numbers, i * i);
+  show(numbers);
+  numbers := reverse(numbers);
+  show(numbers);
+
+  while numbers <> nil do
+  begin
+    p := numbers;
+    numbers := numbers^.next;
+    dispose(p);
+  end;
+  show(numbers);
+
+  root := nil;
+  insert(root, 'm');
+  insert(root, 'c');
+  insert(root, 'x');
+  insert(root, 'a');
+  insert(root, 'e');
+  insert(root, 'p');
+  inorder(root);
+  writeln;
+  with root^.left^ do
+    writeln(key, ' ', left^.key, ' ', right^.key);
+  free(root);
+
+  new(counter);
+  counter^ := 41;
+  counter^ := counter^ + 1;
+  writeln(counter^);
+  dispose(counter);
+
+  writeln('Using a pointer after disposing of it stops the program:');
+  writeln(counter^);
+end.
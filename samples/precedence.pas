@@ -0,0 +1,26 @@
+{ Operators bind by Pascal's four precedence levels: unary, multiplying, adding, then relational }
+program Precedence;
+
+const Rest = 17 mod 5;
+
+var a, b : integer;
+var ok : boolean;
+
+begin
+  a := 4;
+  b := 5;
+
+  { The comparison is made last, so this is (a + 1) = b }
+  ok := a + 1 = b;
+  writeln('a + 1 = b: ', ok);
+
+  writeln('2 + 3 * 4 mod 5 = ', 2 + 3 * 4 mod 5);
+  writeln('17 mod 5 = ', Rest, ', -7 mod 3 = ', -7 mod 3, ', 7 div -3 = ', 7 div -3);
+  writeln('+a - -b = ', +a - -b);
+
+  { and binds like *, so the comparisons need parentheses }
+  if not (a > b) and (b mod a = 1) then
+  begin
+    writeln('b is one more than a multiple of a');
+  end;
+end.
@@ -7,7 +7,7 @@ end;
 
 begin
   x := 10;
-  write(x);
+  writeln(x);
   p;
-  write(x);
+  writeln(x);
 end.
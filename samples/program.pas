@@ -0,0 +1,13 @@
+{ A program heading names the program, and a uses clause names the units it needs }
+program Greeting(input, output);
+
+uses System;
+
+const Times = 3;
+
+var i : integer;
+
+begin
+  for i := 1 to Times do
+    writeln('Hello from program Greeting, line ', i);
+end.
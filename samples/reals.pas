@@ -0,0 +1,35 @@
+var r, area : real;
+var n : integer;
+var samples : array [1..3] of real;
+
+function circleArea(radius : real) : real;
+begin
+  circleArea := 3.14159 * radius * radius;
+end;
+
+function average(a, b : real) : real;
+begin
+  average := (a + b) / 2;
+end;
+
+begin
+  r := 2.5;
+  area := circleArea(r);
+  writeln('area: ', area);
+
+  n := 7;
+  writeln('7 / 2 = ', n / 2);
+  writeln('7 div 2 = ', n div 2);
+  writeln('mixed: ', n + 0.5, ' ', -r);
+
+  samples[1] := 1;
+  samples[2] := 2.25;
+  samples[3] := average(samples[1], 4);
+  writeln(samples[1], ' ', samples[2], ' ', samples[3]);
+
+  if area > 19 then
+  begin
+    writeln('large circle');
+  end;
+  writeln(samples[3] = 2.5);
+end.
@@ -0,0 +1,77 @@
+const Max = 3;
+
+type Point = record
+       x, y : integer;
+     end;
+     Person = record
+       initial : char;
+       age : integer;
+       height : real;
+       home : Point;
+       scores : array [1..Max] of integer;
+     end;
+     Line = array [1..2] of Point;
+
+var p, q : Point;
+var segment : Line;
+var bob, ann : Person;
+var i, x : integer;
+
+procedure show(pt : Point);
+begin
+  writeln('(', pt.x, ', ', pt.y, ')');
+end;
+
+procedure move(var pt : Point; dx, dy : integer);
+begin
+  pt.x := pt.x + dx;
+  pt.y := pt.y + dy;
+end;
+
+function total(who : Person) : integer;
+var k, sum : integer;
+begin
+  sum := 0;
+  for k := 1 to Max do
+    sum := sum + who.scores[k];
+  who.age := 0;
+  total := sum;
+end;
+
+begin
+  p.x := 1;
+  p.y := 2;
+  q := p;
+  move(q, 10, 20);
+  show(p);
+  show(q);
+
+  segment[1] := p;
+  segment[2] := q;
+  segment[2].y := segment[1].x + 99;
+  show(segment[2]);
+
+  bob.initial := 'B';
+  bob.age := 42;
+  bob.height := 1.8;
+  bob.home := q;
+  for i := 1 to Max do
+    bob.scores[i] := i * i;
+  writeln(bob.initial, ' is ', bob.age, ', ', bob.height, ' tall, and scored ', total(bob));
+  writeln('Still ', bob.age);
+
+  x := 7;
+  ann := bob;
+  with ann, home do
+  begin
+    initial := 'A';
+    age := age - 10;
+    x := x + 1;
+    scores[Max] := 0;
+  end;
+  writeln(ann.initial, ' ', ann.age, ' ', ann.home.x, ' ', ann.scores[Max], ' ', total(ann));
+  writeln(bob.initial, ' ', bob.age, ' ', bob.home.x, ' ', bob.scores[Max], ' ', x);
+
+  with segment[1] do
+    writeln(x * 100 + y);
+end.
@@ -0,0 +1,74 @@
+var n : integer;
+
+function factorial(n : integer) : integer;
+begin
+  if n <= 1 then
+  begin
+    factorial := 1;
+  end
+  else begin
+    factorial := n * factorial(n - 1);
+  end;
+end;
+
+function ackermann(m, n : integer) : integer;
+begin
+  if m = 0 then
+  begin
+    ackermann := n + 1;
+  end
+  else begin
+    if n = 0 then
+    begin
+      ackermann := ackermann(m - 1, 1);
+    end
+    else begin
+      ackermann := ackermann(m - 1, ackermann(m, n - 1));
+    end;
+  end;
+end;
+
+function isOdd(n : integer) : integer; forward;
+
+function isEven(n : integer) : integer;
+begin
+  if n = 0 then
+  begin
+    isEven := 1;
+  end
+  else begin
+    isEven := isOdd(n - 1);
+  end;
+end;
+
+function isOdd(n : integer) : integer;
+begin
+  if n = 0 then
+  begin
+    isOdd := 0;
+  end
+  else begin
+    isOdd := isEven(n - 1);
+  end;
+end;
+
+procedure countdown(n : integer);
+var next : integer;
+begin
+  if n > 0 then
+  begin
+    next := n - 1;
+    countdown(next);
+  end;
+  writeln(n);
+end;
+
+begin
+  n := 10;
+  writeln(factorial(n));
+  writeln(ackermann(2, 3));
+  writeln(isEven(7));
+  writeln(isOdd(7));
+  countdown(3);
+  writeln(n);
+end.
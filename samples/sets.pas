@@ -0,0 +1,77 @@
+type Color = (Red, Green, Blue, Yellow);
+     Chars = set of char;
+     Colors = set of Color;
+     Digits = set of 0..9;
+     Word = array [1..11] of char;
+
+var vowels, letters, consonants, seen : Chars;
+var warm, cool, palette : Colors;
+var evens, odds, small : Digits;
+var text : Word;
+var c : Color;
+var i, count : integer;
+var ch : char;
+
+procedure show(s : Digits);
+var d : integer;
+begin
+  write('[');
+  for d := 0 to 9 do
+    if d in s then
+    begin
+      write(' ', d);
+    end;
+  writeln(' ]');
+end;
+
+function vowelCount(var w : Word) : integer;
+var k, n : integer;
+begin
+  n := 0;
+  for k := 1 to 11 do
+    if w[k] in vowels then
+    begin
+      n := n + 1;
+    end;
+  vowelCount := n;
+end;
+
+begin
+  vowels := ['a', 'e', 'i', 'o', 'u'];
+  letters := ['a'..'z', 'A'..'Z'];
+  consonants := letters - vowels - ['A'..'Z'];
+  writeln('b' in consonants, ' ', 'e' in consonants, ' ', 'Q' in letters, ' ', '3' in letters);
+
+  text[1] := 'h'; text[2] := 'e'; text[3] := 'l'; text[4] := 'l'; text[5] := 'o';
+  text[6] := ' '; text[7] := 'w'; text[8] := 'o'; text[9] := 'r'; text[10] := 'l'; text[11] := 'd';
+
+  seen := [];
+  count := 0;
+  for i := 1 to 11 do
+  begin
+    ch := text[i];
+    if (ch in letters) and not (ch in seen) then
+    begin
+      count := count + 1;
+      seen := seen + [ch];
+    end;
+  end;
+  writeln('Distinct letters: ', count, ', vowels: ', vowelCount(text));
+
+  warm := [Red, Yellow];
+  cool := [Green..Blue];
+  palette := warm + cool;
+  for c := Red to Yellow do
+    write(c in warm, ' ');
+  writeln;
+  writeln(palette = [Red..Yellow], ' ', warm * cool = [], ' ', warm <= palette, ' ', cool >= palette, ' ', warm <> cool);
+
+  evens := [0, 2, 4, 6, 8];
+  odds := [0..9] - evens;
+  small := [0..4];
+  show(odds);
+  show(evens * small);
+  show(evens + small);
+  show(small - evens);
+  show([]);
+end.
@@ -0,0 +1,33 @@
+var x, y : integer;
+var a : array [-2..2] of integer;
+
+function abs(n : integer) : integer;
+begin
+  if n < 0 then
+  begin
+    abs := -n;
+  end
+  else begin
+    abs := n;
+  end;
+end;
+
+begin
+  x := 3;
+  y := x - 10;
+  writeln(y);
+  writeln(-y);
+  writeln(-(x * 4) + 2);
+  writeln(y div 2);
+  writeln(abs(y) * -1);
+
+  if y < x then
+  begin
+    writeln(1);
+  end;
+
+  a[-2] := -20;
+  a[2] := 20;
+  writeln(a[-2] + a[2]);
+  writeln(a[-2]);
+end.
@@ -0,0 +1,32 @@
+var i, total : integer;
+var done : boolean;
+
+procedure greet(initial : char);
+begin
+  writeln('Hello, ', initial, '!');
+end;
+
+begin
+  writeln('String literals may contain ''quotes'' and spaces.');
+  greet('R');
+
+  total := 0;
+  i := 1;
+  while i <= 4 do
+  begin
+    total := total + i;
+    write(i);
+    if i < 4 then
+    begin
+      write(' + ');
+    end;
+    i := i + 1;
+  end;
+  writeln(' = ', total);
+
+  done := total = 10;
+  writeln('done: ', done);
+  writeln;
+  writeln('Hello, ', 'R', '!');
+  write('no newline at the end');
+end.
@@ -0,0 +1,60 @@
+const Size = 5;
+
+type Index = integer;
+     Vec = array [1..Size] of integer;
+     Counts = array ['a'..'e'] of Index;
+     Matrix = array [1..3] of Vec;
+
+var v, w : Vec;
+var tally : Counts;
+var m : Matrix;
+var i, j : Index;
+var c : char;
+
+procedure fill(var target : Vec; start : integer);
+var k : integer;
+begin
+  for k := 1 to Size do
+    target[k] := start + k;
+end;
+
+function sum(values : Vec) : integer;
+var k, total : integer;
+begin
+  total := 0;
+  for k := 1 to Size do
+    total := total + values[k];
+  values[1] := 0;
+  sum := total;
+end;
+
+procedure show(values : Vec);
+var k : integer;
+begin
+  for k := 1 to Size do
+    write(values[k], ' ');
+  writeln;
+end;
+
+begin
+  fill(v, 0);
+  show(v);
+  writeln('sum = ', sum(v), ', first is still ', v[1]);
+
+  w := v;
+  w[Size] := 100;
+  show(w);
+  show(v);
+
+  for c := 'a' to 'e' do
+    tally[c] := ord(c) - ord('a');
+  writeln(tally['c']);
+
+  for i := 1 to 3 do
+  begin
+    fill(m[i], i * 10);
+    for j := 1 to Size do
+      write(m[i][j], ' ');
+    writeln;
+  end;
+end.
@@ -4,5 +4,5 @@ begin
   x := 10;
   y := 5;
 
-  write(x + y);
+  writeln(x + y);
 end.
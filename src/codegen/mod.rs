@@ -4,4 +4,7 @@ pub trait CodeGenerator {
     fn op(&mut self, opcode: &str);
     fn data(&mut self, data: String, dtype: &str, dsize: usize);
     fn fill(&mut self, data: String, dtype: &str, dsize: usize);
+    fn str_const(&mut self, value: &str);
+    fn emit_consts(&mut self);
+    fn emit_name(&mut self);
 }
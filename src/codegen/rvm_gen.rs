@@ -4,11 +4,17 @@ pub struct RvmGenerator {
     pub i_ptr: usize,
     pub data_addr: u32,
     pub code: Vec<u8>,
+
+    // Program name from the heading, if there is one
+    pub name: Option<String>,
+
+    // String constants, with the holes left for their addresses in the code
+    consts: Vec<(String, Vec<usize>)>,
 }
 
 impl RvmGenerator {
     pub fn new() -> RvmGenerator {
-        RvmGenerator { i_ptr : 0, data_addr: 0, code: vec![] }
+        RvmGenerator { i_ptr : 0, data_addr: 0, code: vec![], name: None, consts: vec![] }
     }
 }
 
@@ -23,10 +29,21 @@ impl CodeGenerator for RvmGenerator {
             "OP_LOAD" => self.code.push(0x04),
             "OP_CALL" => self.code.push(0x05),
             "OP_RETURN" => self.code.push(0x06),
+            "OP_ENTER" => self.code.push(0x07),
+            "OP_LEAVE" => self.code.push(0x08),
+            "OP_LADDR" => self.code.push(0x09),
+            "OP_FRAME" => self.code.push(0x0A),
+            "OP_LOADN" => self.code.push(0x0B),
+            "OP_STOREN" => self.code.push(0x0C),
+            "OP_CHECK" => self.code.push(0x0D),
             "OP_ADD" => self.code.push(0x10),
             "OP_SUB" => self.code.push(0x11),
             "OP_MULT" => self.code.push(0x12),
             "OP_DIVI" => self.code.push(0x13),
+            "OP_NEG" => self.code.push(0x14),
+            "OP_MOD" => self.code.push(0x17),
+            "OP_ITOF" => self.code.push(0x15),
+            "OP_ITOF2" => self.code.push(0x16),
             "OP_EQUAL" => self.code.push(0x40),
             "OP_NEQUAL" => self.code.push(0x41),
             "OP_AND" => self.code.push(0x42),
@@ -35,18 +52,58 @@ impl CodeGenerator for RvmGenerator {
             "OP_LTE" => self.code.push(0x45),
             "OP_GT" => self.code.push(0x46),
             "OP_GTE" => self.code.push(0x47),
+            "OP_NOT" => self.code.push(0x48),
+            "OP_FADD" => self.code.push(0x50),
+            "OP_FSUB" => self.code.push(0x51),
+            "OP_FMULT" => self.code.push(0x52),
+            "OP_FDIV" => self.code.push(0x53),
+            "OP_FNEG" => self.code.push(0x54),
+            "OP_FEQUAL" => self.code.push(0x60),
+            "OP_FNEQUAL" => self.code.push(0x61),
+            "OP_FLT" => self.code.push(0x64),
+            "OP_FLTE" => self.code.push(0x65),
+            "OP_FGT" => self.code.push(0x66),
+            "OP_FGTE" => self.code.push(0x67),
+            "OP_SEMPTY" => self.code.push(0x70),
+            "OP_SADD" => self.code.push(0x71),
+            "OP_SRANGE" => self.code.push(0x72),
+            "OP_SUNION" => self.code.push(0x73),
+            "OP_SDIFF" => self.code.push(0x74),
+            "OP_SINTER" => self.code.push(0x75),
+            "OP_SEQL" => self.code.push(0x76),
+            "OP_SNEQL" => self.code.push(0x77),
+            "OP_SLTE" => self.code.push(0x78),
+            "OP_SGTE" => self.code.push(0x79),
+            "OP_IN" => self.code.push(0x7A),
+            "OP_ALLOC" => self.code.push(0x80),
+            "OP_FREE" => self.code.push(0x81),
+            "OP_DEREF" => self.code.push(0x82),
             "OP_WRITE" => self.code.push(0x20),
+            "OP_WRITEB" => self.code.push(0x21),
+            "OP_WRITEC" => self.code.push(0x22),
+            "OP_WRITES" => self.code.push(0x23),
+            "OP_WRITELN" => self.code.push(0x24),
+            "OP_WRITER" => self.code.push(0x25),
+            "OP_READ" => self.code.push(0x26),
+            "OP_READC" => self.code.push(0x27),
+            "OP_READR" => self.code.push(0x28),
+            "OP_READS" => self.code.push(0x29),
+            "OP_READLN" => self.code.push(0x2A),
+            "OP_EOF" => self.code.push(0x2B),
+            "OP_EOLN" => self.code.push(0x2C),
             "OP_JTRUE" => self.code.push(0x30),
             "OP_JFALSE" => self.code.push(0x31),
             "OP_JMP" => self.code.push(0x32),
+            "OP_JTABLE" => self.code.push(0x33),
             _ => panic!("Invalid op code given.")
         }
     }
 
     fn data(&mut self, data: String, dtype: &str, dsize: usize) {
-        let value_parsed;
-        match dtype {
-            "u32" => value_parsed = data.parse::<u32>().expect("Expected u32 conversion"),
+        let value_parsed = match dtype {
+            "u32" => data.parse::<u32>().expect("Expected u32 conversion"),
+            "i32" => data.parse::<i32>().expect("Expected i32 conversion") as u32,
+            "f32" => data.parse::<f32>().expect("Expected f32 conversion").to_bits(),
             _ => panic!("Invalid data type specified.")
         };
 
@@ -56,9 +113,9 @@ impl CodeGenerator for RvmGenerator {
     }
 
     fn fill(&mut self, data: String, dtype: &str, _dsize: usize) {
-        let value_parsed;
-        match dtype {
-            "u32" => value_parsed = data.parse::<u32>().expect("Expected u32 conversion"),
+        let value_parsed = match dtype {
+            "u32" => data.parse::<u32>().expect("Expected u32 conversion"),
+            "i32" => data.parse::<i32>().expect("Expected i32 conversion") as u32,
             _ => panic!("Invalid data type specified.")
         };
 
@@ -68,4 +125,50 @@ impl CodeGenerator for RvmGenerator {
         self.code[self.i_ptr + 2] = value_bytes[2];
         self.code[self.i_ptr + 3] = value_bytes[3];
     }
+
+    // Emit a hole for the address of a string constant, which is only known once the constant
+    // area has been laid out after the code.
+    fn str_const(&mut self, value: &str) {
+        let hole = self.i_ptr;
+        self.data("0".to_string(), "u32", 4);
+
+        match self.consts.iter_mut().find(|(s, _)| s == value) {
+            Some((_, holes)) => holes.push(hole),
+            None => self.consts.push((value.to_string(), vec![hole])),
+        }
+    }
+
+    // Append the read-only constant area to the end of the code. Each string is stored as its
+    // length in bytes followed by the characters themselves.
+    fn emit_consts(&mut self) {
+        for (value, holes) in std::mem::take(&mut self.consts) {
+            let addr = self.i_ptr;
+            let bytes: Vec<u8> = value.chars().map(|c| c as u8).collect();
+            self.data(bytes.len().to_string(), "u32", 4);
+            self.i_ptr += bytes.len();
+            self.code.extend(bytes);
+
+            let save = self.i_ptr;
+            for hole in holes {
+                self.i_ptr = hole;
+                self.fill(addr.to_string(), "u32", 4);
+            }
+            self.i_ptr = save;
+        }
+    }
+
+    // End the image with the program's name, stored like a string constant, and then its address.
+    // An unnamed program just ends with a 0 address.
+    fn emit_name(&mut self) {
+        let Some(name) = self.name.clone() else {
+            self.data("0".to_string(), "u32", 4);
+            return
+        };
+        let addr = self.i_ptr;
+        let bytes: Vec<u8> = name.chars().map(|c| c as u8).collect();
+        self.data(bytes.len().to_string(), "u32", 4);
+        self.i_ptr += bytes.len();
+        self.code.extend(bytes);
+        self.data(addr.to_string(), "u32", 4);
+    }
 }
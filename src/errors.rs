@@ -1,4 +1,5 @@
 use crate::tokens::Token;
+use crate::tokens::TokenType;
 
 pub fn scanner_error(msg: String, value : String, lineno : usize, colno : usize) -> String {
     let msg = format!(
@@ -13,13 +14,40 @@ pub fn scanner_error(msg: String, value : String, lineno : usize, colno : usize)
 }
 
 pub fn parser_error(expected: &str, got: Token) -> String {
+    // Quote what was found as it was written, unless it is the end of the file
+    let found = match got.token_type {
+        TokenType::Eof => got.token_type.to_str().to_string(),
+        _ => format!("{} [ {} ]", got.token_type.to_str(), got.token_value),
+    };
     let msg = format!(
         "Parser Error: Expected {} but found {} ({}:{})",
         expected,
-        got.token_type.to_str(),
+        found,
         got.src_info.0,
         got.src_info.1
     );
 
     msg.to_string()
 }
+
+pub fn semantic_error(msg: &str, tok: &Token) -> String {
+    let msg = format!(
+        "Semantic Error: {} [ {} ] ({}:{})",
+        msg,
+        tok.token_value,
+        tok.src_info.0,
+        tok.src_info.1
+    );
+
+    msg.to_string()
+}
+
+pub fn runtime_error(msg: &str, ip: usize) -> String {
+    let msg = format!(
+        "Runtime Error: {} (ip: {:#06x})",
+        msg,
+        ip
+    );
+
+    msg.to_string()
+}
@@ -4,6 +4,7 @@
  */
 mod errors;
 mod tokens;
+mod types;
 mod symbtab;
 mod scanner;
 mod parser;
@@ -37,13 +38,29 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
     println!("Compiling program [{}]cargo...\n=================================================\n", &config.filename);
 
     let mut parser = Parser::new(scan);
-    parser.parse()?;
+    if let Err(err) = parser.parse() {
+        return Err(in_program(&parser.gen.name, err));
+    }
 
     println!("The generated code is: {:02x?}\n", &parser.gen.code[..]);
-    println!("Executing program [{}]cargo...\n=================================================\n", &config.filename);
 
-    let mut rvm = RvmMachine::new(parser.gen.code);
-    rvm.exec();
+    // From here on the program's name comes from the image itself
+    let mut rvm = RvmMachine::new(parser.gen.code, parser.gen.data_addr as usize);
+    match &rvm.name {
+        Some(name) => println!("Executing program {} [{}]cargo...\n=================================================\n", name, &config.filename),
+        None => println!("Executing program [{}]cargo...\n=================================================\n", &config.filename),
+    }
+    if let Err(err) = rvm.exec() {
+        return Err(in_program(&rvm.name, err));
+    }
 
     Ok(())
 }
+
+// Errors from a program with a heading say which program they come from
+fn in_program(name: &Option<String>, err: String) -> Box<dyn Error> {
+    match name {
+        Some(name) => format!("In program {}: {}", name, err).into(),
+        None => err.into(),
+    }
+}
@@ -7,7 +7,6 @@
 use std::env;
 use std::process;
 
-use rascal;
 use rascal::Config;
 
 fn main() {
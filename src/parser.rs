@@ -5,43 +5,143 @@
 * will read a token and apply a grammar production rule to it.
 *
 * The language grammar specification
-*       <prog> -> <decls> <body>. EOF
-*       <decls> -> var <namelist> <decl-tail> | const <namelist> <decl-tail> |... type, label, procedure, function
-*       <namelist> -> id<namelist-tail>; : <type>
+*       <prog> -> [<heading>] [<uses>] <decls> <body>. EOF
+*       <heading> -> program id [( <namelist> )] ;
+*       <uses> -> uses <namelist> ;
+*       <decls> -> var <var-decls> <decl-tail> | const <namelist> <decl-tail> |... type, label, procedure, function
+*       <label-decls> -> label intlit {, intlit} ;
+*       <const-decls> -> const id = <const-expr> ; {id = <const-expr> ;}
+*       <type-decls> -> type id = <type> ; {id = <type> ;}
+*       <var-decls> -> <namelist> : <type>; | <namelist> : <type>; <var-decls>
+*       <type> -> integer | boolean | char | real | id | <enum> | <range> | <set> | <pointer> | <array> | <record>
+*       <set> -> set of <type>
+*       <pointer> -> ^ id
+*       <enum> -> ( <namelist> )
+*       <range> -> <const> .. <const>
+*       <array> -> array [ <type> {, <type>} ] of <type>
+*       <record> -> record <namelist> : <type> {; <namelist> : <type>} [;] end
+*       <namelist> -> id<namelist-tail>
 *       <namelist-tail> NULL | ,<namelist>
 *       <decl-tail> -> NULL | ;<decls>
+*       <proc-decl> -> procedure id <params>; <decls> <begin-st>
+*       <func-decl> -> function id <params> : <type>; <decls> <begin-st>
+*       <forward-decl> -> procedure id <params>; forward | function id <params> : <type>; forward
+*       <params> -> NULL | ( <param-group> {; <param-group>} )
+*       <param-group> -> [var] id {, id} : <param-type>
+*       <param-type> -> integer | boolean | char | real | id
 *       <body> -> <begin-st>
 *       <begin-st> -> begin <stats> <end>
 *       <stats> -> NULL | <repeat st><stat-tail> | <while st> ... <if st> ... ...
 *       <stat-tail> -> ;<stats>
+*       <proc st> -> id | id ( <expr> {, <expr>} )
+*       <new st> -> new ( <var> )
+*       <dispose st> -> dispose ( <expr> )
+*       <write st> -> write ( <write-arg> {, <write-arg>} ) | writeln | writeln ( <write-arg> {, <write-arg>} )
+*       <write-arg> -> <expr> | string
+*       <read st> -> read ( <var> {, <var>} ) | readln | readln ( <var> {, <var>} )
 *       <repeat st> -> repeat <stats> until <condition>
 *       <while st> -> while <condition> do <begin-st>
+*       <case st> -> case <expr> of <case-arm> {; <case-arm>} [;] [else <stats> | otherwise <stats>] end
+*       <case-arm> -> <case-label> {, <case-label>} : <stat>
+*       <case-label> -> <const> | <const> .. <const>
+*       <for st> -> for id := <expr> to <expr> do <stat> | for id := <expr> downto <expr> do <stat>
+*       <stat> -> [intlit :] <begin-st> | [intlit :] <assign st> | ... | intlit : NULL
+*       <goto st> -> goto intlit
+*       <with st> -> with <var> {, <var>} do <stat>
+*       <var> -> id | <var> [ <expr> {, <expr>} ] | <var> . id | <var> ^
 *       <if st> -> if <condition> then <stats> | if <condition> then <stats> else <stats>
 *
 * The expression grammer specification (removing instances of immediate left recursion) is as follows:
-*       E  -> TE'
-*       E' -> +TE' | -TE' | NULL
+*       E  -> S | S = S | S <> S | S < S | S <= S | S > S | S >= S | S in S
+*
+*       S  -> TS'
+*       S' -> +TS' | -TS' | or TS' | NULL
 *
 *       T  -> FT'
-*       T' -> *FT' | /FT' | NULL
+*       T' -> *FT' | /FT' | div FT' | mod FT' | and FT' | NULL
 *
-*       F  -> (E) | lit | id | +F | -F
+*       F  -> (E) | lit | id | id(E, ...) | ord(E) | chr(E) | succ(E) | pred(E) | [ <set-elems> ] | nil | eof | eoln | +F | -F | not F
+*       <set-elems> -> NULL | <set-elem> {, <set-elem>}
+*       <set-elem> -> E | E .. E
 */
+use std::collections::HashMap;
+
 use crate::errors::parser_error;
+use crate::errors::semantic_error;
+use crate::tokens::Token;
 use crate::tokens::TokenType;
+use crate::types::ArrayDef;
+use crate::types::EnumDef;
+use crate::types::SubrangeDef;
+use crate::types::Field;
+use crate::types::PointerDef;
+use crate::types::RecordDef;
+use crate::types::SetDef;
+use crate::types::Type;
+use crate::types::TypeTable;
+use crate::types::Value;
 use crate::scanner::Scanner;
+use crate::symbtab::SymbTab;
 
 use crate::codegen::CodeGenerator;
 use crate::codegen::rvm_gen::RvmGenerator;
 
-// Currently unused, may be used in the future to implement a proper type system.
-// enum Type {
-//     I, R, B, C
-// }
+// The built-in units a uses clause may name
+const UNITS: [&str; 1] = ["system"];
+
+// The room the RVM has for global variables, below its heap, and for a stack frame on its stack
+const DATA_SIZE: u32 = 0x4000_0000;
+const FRAME_SIZE: u32 = 0x10000;
 
 pub struct Parser {
     scan : Scanner,
     pub gen: RvmGenerator,
+
+    // The structured types declared so far
+    types: TypeTable,
+
+    // Pointer types whose target was named before being declared, to be filled in at the end of
+    // the section declaring them
+    pending_pointers: Vec<(usize, Token)>,
+
+    // Whether values stored into subranges, and stepped by succ and pred, are checked at runtime.
+    // Switched with the {$R+} and {$R-} directives.
+    range_checks: bool,
+
+    // Bytes of local variables reserved so far in the frame of the procedure being compiled
+    frame_size: Option<u32>,
+
+    // Procedures declared forward whose bodies are yet to come, with the holes left by their calls
+    forwards: HashMap<String, (Token, Vec<usize>)>,
+
+    // Control variables of the for loops currently being compiled, which may not be assigned to
+    for_vars: Vec<Token>,
+
+    // Records opened by the with statements being compiled, innermost last, each with the hidden
+    // variable holding its address
+    withs: Vec<(Token, Type)>,
+
+    // Labels declared by the routine being compiled
+    labels: HashMap<String, Label>,
+
+    // The statements enclosing the one being compiled, innermost last, and the count used so far.
+    // Statements directly within a routine's body belong to block 0.
+    blocks: Vec<usize>,
+    block_count: usize,
+}
+
+struct Label {
+    decl: Token,
+
+    // Where the label is defined: the address of its statement and the block holding it
+    addr: Option<usize>,
+    block: usize,
+
+    // Jumps to the label from before its definition, with the blocks that enclosed each of them
+    gotos: Vec<(usize, Vec<usize>, Token)>,
+
+    // Whether any goto jumps to the label
+    used: bool,
 }
 
 #[allow(non_snake_case)]
@@ -49,16 +149,33 @@ impl Parser {
     pub fn new (scan : Scanner) -> Parser {
         Parser {
             scan,
-            gen: RvmGenerator::new()
+            gen: RvmGenerator::new(),
+            types: TypeTable::new(),
+            pending_pointers: vec![],
+            range_checks: true,
+            frame_size: None,
+            forwards: HashMap::new(),
+            for_vars: vec![],
+            withs: vec![],
+            labels: HashMap::new(),
+            blocks: vec![],
+            block_count: 0,
         }
     }
 
     pub fn parse(&mut self) -> Result<(), String> {
+        self.directives()?;
+        self.heading()?;
+        self.uses()?;
         self.decls()?;
+        self.check_forwards()?;
         self.body()?;
+        self.check_labels()?;
         self.match_tok(TokenType::Dot)?;
         self.match_tok(TokenType::Eof)?;
         self.gen.op("OP_EXIT");
+        self.gen.emit_consts();
+        self.gen.emit_name();
         Ok(())
     }
 
@@ -74,419 +191,2297 @@ impl Parser {
 
     fn match_tok(&mut self, tok: TokenType) -> Result<(), String> {
         self.check_tok(tok)?;
-
-        match self.scan.get_token() {
-            Ok(()) => Ok(()),
-            Err(e) => Err(e)
-        }
+        self.next_token()
     }
 
-    // === GRAMMAR PRODUCTIONS ====================================================================
-    fn decls(&mut self) -> Result<(), String> {
-        while
-        self.check_tok(TokenType::Var).is_ok() ||
-        self.check_tok(TokenType::Procedure).is_ok()
-        {
-            match self.scan.cur_token.token_type {
-                TokenType::Var => {
-                    self.match_tok(TokenType::Var)?;
-                    self.namelist()?;
-                    self.match_tok(TokenType::Colon)?;
-                    self.decl_type()?;
-                    self.decl_tail()?;
-                },
-                TokenType::Procedure => {
-                    self.match_tok(TokenType::Procedure)?;
-                    self.match_tok(TokenType::Ident)?;
-                    self.match_tok(TokenType::Semi)?;
-
-                    // Create a hole to JMP/skip the procedure body on first run.
-                    self.gen.op("OP_PUSH");
-                    let hole = self.gen.i_ptr;
-                    self.gen.data("0".to_string(), "u32", 4);
-                    self.gen.op("OP_JMP");
-
-                    // Set the procedure's address to after the jump
-                    self.scan.symbol_table.set_idents_to(TokenType::AProcedure, self.gen.i_ptr as u32, 4);
-
-                    self.begin_st()?;
-                    self.gen.op("OP_RETURN");
-
-                    // Fill the hole with the instruction pointer after the return call
-                    let save = self.gen.i_ptr;
-                    self.gen.i_ptr = hole;
-                    self.gen.fill(save.to_string(), "u32", 4);
-
-                    // Restore i_ptr back to the latest instruction
-                    self.gen.i_ptr = save;
+    fn next_token(&mut self) -> Result<(), String> {
+        self.scan.get_token()?;
+        self.directives()
+    }
 
-                    self.decl_tail()?;
-                },
-                _ => panic!("Declarations must begin with PROCEDURE or VAR keywords."),
+    // Act on any directives at the current token, which may come between any two tokens
+    fn directives(&mut self) -> Result<(), String> {
+        while self.scan.cur_token.token_type == TokenType::Directive {
+            match &self.scan.cur_token.token_value.to_ascii_uppercase()[..] {
+                "R+" => self.range_checks = true,
+                "R-" => self.range_checks = false,
+                // Like other compilers, ignore directives that are not understood
+                _ => {}
             }
+            self.scan.get_token()?;
         }
         Ok(())
     }
 
-    fn namelist(&mut self) -> Result<(), String> {
-        self.match_tok(TokenType::Ident)?;
-        self.namelist_tail()?;
-        Ok(())
+    // Check for an identifier that is being declared. It may shadow a name which is already declared.
+    fn check_name(&self) -> bool {
+        matches!(
+            self.scan.cur_token.token_type,
+            TokenType::Ident |
+            TokenType::AConst |
+            TokenType::AType |
+            TokenType::AVar |
+            TokenType::AProcedure |
+            TokenType::AFunction
+        )
     }
 
-    fn namelist_tail(&mut self) -> Result<(), String> {
-        if self.scan.cur_token.token_type == TokenType::Comma {
-            self.match_tok(TokenType::Comma)?;
-            self.namelist()?;
+    fn match_name(&mut self) -> Result<Token, String> {
+        let tok = self.scan.cur_token.clone();
+        if !self.check_name() {
+            return Err(parser_error(TokenType::Ident.to_str(), tok));
         }
-        Ok(())
-    }
-
-    fn decl_type(&mut self) -> Result<(), String> {
-        while
-        self.check_tok(TokenType::Integer).is_ok() ||
-        self.check_tok(TokenType::Array).is_ok() {
-            match self.scan.cur_token.token_type {
-                TokenType::Integer => {
-                    self.match_tok(TokenType::Integer)?;
-
-                    let num_int_vars = self.scan.symbol_table.set_idents_to(TokenType::AVar, self.gen.data_addr, 4);
 
-                    self.gen.data_addr += num_int_vars * 4;
-                },
-                TokenType::Array => {
-                    self.match_tok(TokenType::Array)?;
-                    self.match_tok(TokenType::LBrack)?;
+        self.next_token()?;
+        Ok(Token::new(TokenType::Ident, tok.token_value, tok.src_info))
+    }
 
-                    let lo = self.scan.cur_token.token_value.parse::<u32>().expect("Lo should be int lit.");
-                    self.match_tok(TokenType::IntLit)?;
+    fn check_type(&self, found: Type, expected: Type, tok: &Token) -> Result<(), String> {
+        if !self.types.compatible(found, expected) {
+            let msg = format!("Expected {} but found {}", self.types.name(expected), self.types.name(found));
+            return Err(semantic_error(&msg, tok));
+        }
+        Ok(())
+    }
 
-                    self.match_tok(TokenType::Range)?;
+    // Check that a value of type `found`, just pushed onto the stack, can be stored where a value
+    // of type `expected` is wanted. Integers are promoted to reals on the way.
+    fn coerce(&mut self, found: Type, expected: Type, tok: &Token) -> Result<(), String> {
+        if found == Type::Integer && expected == Type::Real {
+            self.gen.op("OP_ITOF");
+            return Ok(())
+        }
 
-                    let hi = self.scan.cur_token.token_value.parse::<u32>().expect("Hi should be int lit.");
-                    self.match_tok(TokenType::IntLit)?;
+        // Values of a subrange's base type are checked against its bounds at runtime
+        if let Type::Subrange(id) = expected {
+            let def = self.types.subrange(id);
+            let (base, low, high) = (def.base, def.low, def.high);
+            self.check_type(found, base, tok)?;
+            self.check_range(low, high);
+            return Ok(())
+        }
+        self.check_type(found, expected, tok)
+    }
 
-                    self.match_tok(TokenType::RBrack)?;
-                    self.match_tok(TokenType::Of)?;
+    fn check_range(&mut self, low: i32, high: i32) {
+        if !self.range_checks {
+            return;
+        }
+        self.gen.op("OP_CHECK");
+        self.gen.data(low.to_string(), "i32", 4);
+        self.gen.data(high.to_string(), "i32", 4);
+    }
 
-                    let size = (hi - lo + 1) * 4;
-                    self.scan.symbol_table.set_idents_to_arr(
-                        TokenType::AnArrayVar,
-                        self.gen.data_addr,
-                        size,
-                        lo,
-                        hi
-                    );
-                    self.gen.data_addr += size;
+    fn check_forwards(&self) -> Result<(), String> {
+        // Forward declarations must get their body within the same block
+        let level = self.scan.symbol_table.level();
+        match self.forwards.values().find(|(tok, _)| tok.level == level) {
+            Some((tok, _)) => Err(semantic_error("Missing body for forward declaration", tok)),
+            None => Ok(()),
+        }
+    }
 
-                    self.decl_type()?;
-                },
-                _ => panic!("??"),
-            }
+    fn check_labels(&self) -> Result<(), String> {
+        // Every label jumped to has to be put on one of the block's statements
+        let first_goto = self.labels.values()
+            .filter(|l| l.addr.is_none())
+            .flat_map(|l| l.gotos.iter().map(|(_, _, tok)| tok))
+            .min_by_key(|tok| tok.src_info);
+        if let Some(tok) = first_goto {
+            return Err(semantic_error("Label used but not defined", tok));
         }
 
-        Ok(())
+        // and every label declared has to be jumped to
+        let unused = self.labels.values().filter(|l| !l.used).min_by_key(|l| l.decl.src_info);
+        match unused {
+            Some(label) => Err(semantic_error("Label declared but not used", &label.decl)),
+            None => Ok(()),
+        }
     }
 
-    fn decl_tail(&mut self) -> Result<(), String> {
-        if self.scan.cur_token.token_type == TokenType::Semi {
-            self.match_tok(TokenType::Semi)?;
-            self.decls()?;
+    fn declare(&mut self, mut tok: Token) -> Result<(), String> {
+        if self.scan.symbol_table.in_scope(&tok.token_value) {
+            return Err(semantic_error("Duplicate identifier", &tok));
         }
-        Ok(())
-    }
 
-    fn body(&mut self) -> Result<(), String> {
-        self.begin_st()?;
+        tok.level = self.scan.symbol_table.level();
+        self.scan.symbol_table.declare(tok);
         Ok(())
     }
 
-    fn stats(&mut self) -> Result<(), String> {
-        let tok = self.scan.cur_token.clone();
+    // === GRAMMAR PRODUCTIONS ====================================================================
+    fn decls(&mut self) -> Result<(), String> {
         while
-        self.check_tok(TokenType::AVar).is_ok() ||
-        self.check_tok(TokenType::AnArrayVar).is_ok() ||
-        self.check_tok(TokenType::AProcedure).is_ok() ||
-        self.check_tok(TokenType::Repeat).is_ok() ||
-        self.check_tok(TokenType::While).is_ok() ||
-        self.check_tok(TokenType::If).is_ok() ||
-        self.check_tok(TokenType::Write).is_ok()
+        self.check_tok(TokenType::Label).is_ok() ||
+        self.check_tok(TokenType::Const).is_ok() ||
+        self.check_tok(TokenType::Type).is_ok() ||
+        self.check_tok(TokenType::Var).is_ok() ||
+        self.check_tok(TokenType::Procedure).is_ok() ||
+        self.check_tok(TokenType::Function).is_ok()
         {
-            match tok.token_type {
-                TokenType::AVar => self.assign_st()?,
-                TokenType::AnArrayVar => self.assign_array_st()?,
-                TokenType::AProcedure => self.procedure_st()?,
-                TokenType::Repeat => self.repeat_st()?,
-                TokenType::While => self.while_st()?,
-                TokenType::If => self.if_st()?,
-                TokenType::Write => self.write_st()?,
-                _ => panic!("???")
+            match self.scan.cur_token.token_type {
+                TokenType::Label => self.label_decls()?,
+                TokenType::Const => self.const_decls()?,
+                TokenType::Type => self.type_decls()?,
+                TokenType::Var => self.var_decls()?,
+                TokenType::Procedure | TokenType::Function => {
+                    self.routine_decl()?;
+                    self.decl_tail()?;
+                },
+                _ => panic!("Declarations must begin with PROCEDURE, FUNCTION, VAR, CONST, TYPE or LABEL keywords."),
             }
-            self.stats_tail()?;
         }
         Ok(())
     }
 
-    fn stats_tail(&mut self) -> Result<(), String> {
-        self.match_tok(TokenType::Semi)?;
-        self.stats()?;
-        Ok(())
-    }
-
-    // === STATEMENT HANDLERS =====================================================================
-    fn begin_st(&mut self) -> Result<(), String> {
-        self.match_tok(TokenType::Begin)?;
-        self.stats()?;
-        self.match_tok(TokenType::End)?;
-        Ok(())
-    }
-
-    fn assign_st(&mut self) -> Result<(), String> {
-        let cur_token = self.scan.cur_token.clone();    // Copy this for later
-        self.match_tok(TokenType::AVar)?;
-        self.gen.op("OP_PUSH");
-
-        let addr_val = self.scan.symbol_table.get_addr(&cur_token).expect("ERR: Undeclared variable!");
-        self.gen.data(addr_val.to_string(), "u32", 4);
+    fn routine_decl(&mut self) -> Result<(), String> {
+        let is_func = self.check_tok(TokenType::Function).is_ok();
+        let kind = self.scan.cur_token.token_type;
+        self.match_tok(kind)?;
 
-        self.match_tok(TokenType::OpAssign)?;
-        self.expression()?;
-        self.gen.op("OP_STORE");
-        Ok(())
-    }
+        let mut proc_tok = self.match_name()?;
+        proc_tok.token_type = if is_func { TokenType::AFunction } else { TokenType::AProcedure };
+        proc_tok.level = self.scan.symbol_table.level();
 
-    fn assign_array_st(&mut self) -> Result<(), String> {
-        let arr_token = self.scan.cur_token.clone();    // Copy this for later
-
-        self.match_tok(TokenType::AnArrayVar)?;
-        self.match_tok(TokenType::LBrack)?;
-        self.expression()?; // Parse the index value
-        self.match_tok(TokenType::RBrack)?;
-        self.match_tok(TokenType::OpAssign)?;
+        // The body of a routine declared forward may leave out its parameters and return type
+        let key = SymbTab::key(&proc_tok.token_value);
+        let forward = match self.forwards.get(&key) {
+            Some((decl, _)) if decl.level == proc_tok.level => self.forwards.remove(&key),
+            _ => None,
+        };
+        let mut params = self.formal_params()?;
+        if let Some((decl, _)) = &forward {
+            // Parameters that are repeated have to be the same, even if they are named differently
+            let same_params = params.len() == decl.params.len() &&
+                params.iter().zip(&decl.params).all(|(p, d)| p.dtype == d.dtype && p.by_ref == d.by_ref);
+            if decl.token_type != proc_tok.token_type || (!params.is_empty() && !same_params) {
+                return Err(semantic_error("Declaration does not match forward declaration", &proc_tok));
+            }
 
-        // Convert the index value on the stack into an array element address!
-        let lo = arr_token.low.expect("Array should have low value!");
-        if lo != 0 {
-            self.gen.op("OP_PUSH");
-            self.gen.data(lo.to_string(), "u32", 4);
-            self.gen.op("OP_SUB");
+            if params.is_empty() {
+                params = decl.params.clone();
+            }
         }
 
-        self.gen.op("OP_PUSH");
-        self.gen.data("4".to_string(), "u32", 4);   // Push the size of array elements
-        self.gen.op("OP_MULT");
+        if is_func && (forward.is_none() || self.check_tok(TokenType::Colon).is_ok()) {
+            self.match_tok(TokenType::Colon)?;
+            let tok = self.scan.cur_token.clone();
+            let dtype = self.param_type()?;
+            if !dtype.is_simple() {
+                return Err(semantic_error("Function result must be a simple type", &tok));
+            }
+            if forward.as_ref().is_some_and(|(decl, _)| decl.dtype != Some(dtype)) {
+                return Err(semantic_error("Declaration does not match forward declaration", &proc_tok));
+            }
+            proc_tok.dtype = Some(dtype);
+        } else if let Some((decl, _)) = &forward {
+            proc_tok.dtype = decl.dtype;
+        }
+        self.match_tok(TokenType::Semi)?;
+        proc_tok.params = params.clone();
+
+        if forward.is_none() && self.check_tok(TokenType::Forward).is_ok() {
+            // Calls compiled before the body is declared are filled in later
+            self.match_tok(TokenType::Forward)?;
+            self.declare(proc_tok.clone())?;
+            self.forwards.insert(key, (proc_tok, vec![]));
+            return Ok(());
+        }
 
-        let addr = arr_token.token_addr.expect("Array should have an address by now!");
+        // Create a hole to JMP/skip the procedure body on first run.
         self.gen.op("OP_PUSH");
-        self.gen.data(addr.to_string(), "u32", 4); // Pushi the arrays base addr
-        self.gen.op("OP_ADD");
-
-        self.expression()?;
-        self.gen.op("OP_STORE");
-        Ok(())
-    }
-
-    fn procedure_st(&mut self) -> Result<(), String> {
-        let proc_tok = self.scan.cur_token.clone();    // Copy this for later
-        self.match_tok(TokenType::AProcedure)?;
-        self.gen.op("OP_CALL");
-        self.gen.data(proc_tok.token_addr.expect("Process should have address").to_string(), "u32", 4);
-
-        Ok(())
-    }
-
-    fn repeat_st(&mut self) -> Result<(), String> {
-        self.match_tok(TokenType::Repeat)?;
-        let label = self.gen.i_ptr;
-
-        self.stats()?;
-        self.match_tok(TokenType::Until)?;
-        self.expression()?; // Evaluate condition
+        let hole = self.gen.i_ptr;
+        self.gen.data("0".to_string(), "u32", 4);
+        self.gen.op("OP_JMP");
 
-        self.gen.op("OP_PUSH");
-        self.gen.data(label.to_string(), "u32", 4);
-        self.gen.op("OP_JTRUE");
+        // Set the procedure's address to after the jump
+        proc_tok.token_addr = Some(self.gen.i_ptr as u32);
+        if is_func {
+            // The caller reserves a slot for the return value right below the arguments. Within
+            // the function body, the function's name refers to this slot.
+            proc_tok.frame_offset = Some(-15 - self.params_size(&params) as i32);
+        }
 
-        Ok(())
-    }
+        match forward {
+            Some((_, calls)) => {
+                let save = self.gen.i_ptr;
+                for call in calls {
+                    self.gen.i_ptr = call;
+                    self.gen.fill(save.to_string(), "u32", 4);
+                }
+                self.gen.i_ptr = save;
 
-    fn while_st(&mut self) -> Result<(), String> {
-        self.match_tok(TokenType::While)?;
-        let label = self.gen.i_ptr;
+                self.scan.symbol_table.set_entry(&proc_tok.clone(), &proc_tok);
+            },
+            None => self.declare(proc_tok.clone())?,
+        }
 
-        self.expression()?; // Evaluate condition
-        self.match_tok(TokenType::Do)?;
+        // Parameters and local variables are only visible within the procedure body
+        self.scan.symbol_table.open_scope();
+        for param in params {
+            self.declare(param)?;
+        }
 
+        // Local variables are laid out in the frame, right above the saved frame pointer. Nested
+        // procedures and functions are compiled ahead of the body, like the global ones.
+        let outer_frame_size = self.frame_size.replace(0);
+        let outer_labels = std::mem::take(&mut self.labels);
+        self.decls()?;
+        self.check_forwards()?;
+
+        // Within the body, Result is another name for the function's result, unless a parameter or
+        // local declaration has taken the name
+        if is_func && !self.scan.symbol_table.in_scope("result") {
+            let mut result = Token::new(TokenType::AVar, "Result".to_string(), proc_tok.src_info);
+            result.frame_offset = proc_tok.frame_offset;
+            result.dtype = proc_tok.dtype;
+            self.declare(result)?;
+        }
 
-        self.gen.op("OP_PUSH");
-        let hole = self.gen.i_ptr;
+        // The body may reserve more of the frame for itself (see `for_st`), so the size is filled in
+        // once it has been compiled.
+        self.gen.op("OP_ENTER");
+        let enter_hole = self.gen.i_ptr;
         self.gen.data("0".to_string(), "u32", 4);
-        self.gen.op("OP_JFALSE");
 
         self.begin_st()?;
+        self.gen.op("OP_LEAVE");
+        self.gen.op("OP_RETURN");
+        self.check_labels()?;
+        self.labels = outer_labels;
 
-        self.gen.op("OP_PUSH");
-        self.gen.data(label.to_string(), "u32", 4);
-        self.gen.op("OP_JMP");
+        let frame_size = self.frame_size.expect("Frame size should be set by now!");
+        let save = self.gen.i_ptr;
+        self.gen.i_ptr = enter_hole;
+        self.gen.fill(frame_size.to_string(), "u32", 4);
+        self.gen.i_ptr = save;
+
+        self.frame_size = outer_frame_size;
+        self.scan.symbol_table.close_scope();
+
+        if is_func {
+            // Outside of its body, the function's name may only be used to call it
+            proc_tok.frame_offset = None;
+            self.scan.symbol_table.set_entry(&proc_tok.clone(), &proc_tok);
+        }
 
+        // Fill the hole with the instruction pointer after the return call
         let save = self.gen.i_ptr;
         self.gen.i_ptr = hole;
-
         self.gen.fill(save.to_string(), "u32", 4);
+
+        // Restore i_ptr back to the latest instruction
         self.gen.i_ptr = save;
 
         Ok(())
     }
 
-    fn if_st(&mut self) -> Result<(), String> {
-        self.match_tok(TokenType::If)?;
-        self.expression()?; // Evaluate condition
+    fn formal_params(&mut self) -> Result<Vec<Token>, String> {
+        let mut params = vec![];
+        if self.check_tok(TokenType::LParen).is_err() {
+            return Ok(params);
+        }
 
-        // Set up the jump with temporary 0 address
-        self.gen.op("OP_PUSH");
-        let hole = self.gen.i_ptr;
-        self.gen.data("0".to_string(), "u32", 4);
-        self.gen.op("OP_JFALSE");
+        self.match_tok(TokenType::LParen)?;
+        loop {
+            let by_ref = self.check_tok(TokenType::Var).is_ok();
+            if by_ref {
+                self.match_tok(TokenType::Var)?;
+            }
 
-        self.match_tok(TokenType::Then)?;
-        self.begin_st()?;
+            let names = self.namelist()?;
+            self.match_tok(TokenType::Colon)?;
+            let dtype = self.param_type()?;
 
-        let hole2 = self.gen.i_ptr;
-        if self.check_tok(TokenType::Else).is_ok() {
-            self.gen.data("0".to_string(), "u32", 4);
-            self.gen.op("OP_JMP");
+            for mut param in names {
+                param.token_type = TokenType::AVar;
+                param.dtype = Some(dtype);
+                param.by_ref = by_ref;
+                params.push(param);
+            }
+
+            if self.check_tok(TokenType::Semi).is_err() {
+                break;
+            }
+            self.match_tok(TokenType::Semi)?;
         }
+        self.match_tok(TokenType::RParen)?;
 
-        // Now that statements are done, fill in the previous hole.
-        let save = self.gen.i_ptr;
-        self.gen.i_ptr = hole;
-        self.gen.fill(save.to_string(), "u32", 4);
-        self.gen.i_ptr = save;
+        // Arguments are pushed in order, so the last one ends up right below the static link.
+        let mut offset = -11;
+        for param in params.iter_mut().rev() {
+            offset -= self.param_size(param) as i32;
+            param.frame_offset = Some(offset);
+        }
 
-        if self.check_tok(TokenType::Else).is_ok() {
-            self.match_tok(TokenType::Else)?;
-            self.begin_st()?;
+        Ok(params)
+    }
 
-            let save2 = self.gen.i_ptr;
-            self.gen.i_ptr = hole2;
-            self.gen.fill(save2.to_string(), "u32", 4);
-            self.gen.i_ptr = save2;
+    // A var parameter takes the address of its argument, and a value parameter a copy of it
+    fn param_size(&self, param: &Token) -> u32 {
+        if param.by_ref {
+            return 4;
         }
-        Ok(())
+        self.types.size(param.dtype.expect("Parameter should have a type"))
     }
 
-    fn write_st(&mut self) -> Result<(), String> {
-        self.match_tok(TokenType::Write)?;
-        self.match_tok(TokenType::LParen)?;
-        self.expression()?;
-        self.match_tok(TokenType::RParen)?;
+    fn params_size(&self, params: &[Token]) -> u32 {
+        params.iter().map(|p| self.param_size(p)).sum()
+    }
 
-        self.gen.op("OP_WRITE");
+    // The program parameters (input, output, ...) are accepted for compatibility but not used
+    fn heading(&mut self) -> Result<(), String> {
+        if self.check_tok(TokenType::Program).is_err() {
+            return Ok(());
+        }
+        self.match_tok(TokenType::Program)?;
+        let name = self.match_name()?;
+        if self.check_tok(TokenType::LParen).is_ok() {
+            self.match_tok(TokenType::LParen)?;
+            self.namelist()?;
+            self.match_tok(TokenType::RParen)?;
+        }
+        self.match_tok(TokenType::Semi)?;
+        self.gen.name = Some(name.token_value);
         Ok(())
     }
 
-    // === EXPRESSION PARSERS =====================================================================
-    fn expression(&mut self) -> Result<(), String> {
-        self.term()?;
-        while
-        self.check_tok(TokenType::OpPlus).is_ok() ||
-        self.check_tok(TokenType::OpMinus).is_ok() ||
-        self.check_tok(TokenType::OpOr).is_ok()
-        {
-            let tok = self.scan.cur_token.clone();
-            self.match_tok(tok.token_type)?;
-            self.term()?;
-            self.gen.op(tok.to_op());
-        };
+    // Only the built-in units are known, and everything they provide is always available
+    fn uses(&mut self) -> Result<(), String> {
+        if self.check_tok(TokenType::Uses).is_err() {
+            return Ok(());
+        }
+        self.match_tok(TokenType::Uses)?;
+        for unit in self.namelist()? {
+            if !UNITS.iter().any(|u| u.eq_ignore_ascii_case(&unit.token_value)) {
+                return Err(semantic_error("Unknown unit", &unit));
+            }
+        }
+        self.match_tok(TokenType::Semi)?;
         Ok(())
     }
 
-    fn term(&mut self) -> Result<(), String> {
-        self.factor()?;
-        while
-        self.check_tok(TokenType::OpMult).is_ok() ||
-        self.check_tok(TokenType::OpDivi).is_ok() ||
-        self.check_tok(TokenType::OpAnd).is_ok() ||
-        self.check_tok(TokenType::OpEqual).is_ok() ||
-        self.check_tok(TokenType::OpNEqual).is_ok() ||
-        self.check_tok(TokenType::OpLessThan).is_ok() ||
-        self.check_tok(TokenType::OpLessThanEql).is_ok() ||
-        self.check_tok(TokenType::OpGreatThan).is_ok() ||
-        self.check_tok(TokenType::OpGreatThanEql).is_ok()
-        {
+    fn label_decls(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Label)?;
+        loop {
             let tok = self.scan.cur_token.clone();
-            self.match_tok(tok.token_type)?;
-            self.factor()?;
-            self.gen.op(tok.to_op());
-        };
+            let name = self.label_name()?;
+            if self.labels.contains_key(&name) {
+                return Err(semantic_error("Duplicate label", &tok));
+            }
+            self.labels.insert(name, Label { decl: tok, addr: None, block: 0, gotos: vec![], used: false });
 
+            if self.check_tok(TokenType::Comma).is_err() {
+                break;
+            }
+            self.match_tok(TokenType::Comma)?;
+        }
+        self.match_tok(TokenType::Semi)?;
         Ok(())
     }
 
-    fn factor(&mut self) -> Result<(), String> {
-        let tok = &self.scan.cur_token;
-        match tok.token_type {
-            TokenType::IntLit => {
-                // Push immediate the integer literal onto the stack.
-                self.gen.op("OP_PUSH");
-                self.gen.data(tok.token_value.clone(), "u32", 4);
-
-                self.match_tok(TokenType::IntLit)?;
-            },
-            TokenType::AVar => {
-                // Push immediate the address for the variable onto the stack.
+    // Labels are numbers from 0 to 9999, where leading zeros make no difference
+    fn label_name(&mut self) -> Result<String, String> {
+        let tok = self.scan.cur_token.clone();
+        self.match_tok(TokenType::IntLit)?;
+        match tok.token_value.parse::<u32>() {
+            Ok(value) if value <= 9999 => Ok(value.to_string()),
+            _ => Err(semantic_error("Label out of range", &tok)),
+        }
+    }
+
+    // Constants are evaluated as they are declared, and take up no storage
+    fn const_decls(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Const)?;
+        loop {
+            let mut tok = self.match_name()?;
+            self.match_tok(TokenType::OpEqual)?;
+            let value = self.const_expression()?;
+            self.match_tok(TokenType::Semi)?;
+
+            tok.token_type = TokenType::AConst;
+            tok.dtype = Some(value.dtype());
+            tok.value = Some(value);
+            self.declare(tok)?;
+
+            if !self.check_name() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn type_decls(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Type)?;
+        loop {
+            let mut tok = self.match_name()?;
+            self.match_tok(TokenType::OpEqual)?;
+            let dtype = self.type_spec()?;
+            self.match_tok(TokenType::Semi)?;
+
+            tok.token_type = TokenType::AType;
+            tok.dtype = Some(dtype);
+            self.types.set_name(dtype, &tok.token_value);
+            self.declare(tok)?;
+
+            if !self.check_name() {
+                break;
+            }
+        }
+        self.resolve_pointers()
+    }
+
+    fn var_decls(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Var)?;
+        loop {
+            let names = self.namelist()?;
+            self.match_tok(TokenType::Colon)?;
+            self.decl_type(names)?;
+            self.match_tok(TokenType::Semi)?;
+
+            if !self.check_name() {
+                break;
+            }
+        }
+        self.resolve_pointers()
+    }
+
+    fn namelist(&mut self) -> Result<Vec<Token>, String> {
+        let mut names = vec![self.match_name()?];
+        names.append(&mut self.namelist_tail()?);
+        Ok(names)
+    }
+
+    fn namelist_tail(&mut self) -> Result<Vec<Token>, String> {
+        if self.scan.cur_token.token_type == TokenType::Comma {
+            self.match_tok(TokenType::Comma)?;
+            return self.namelist();
+        }
+        Ok(vec![])
+    }
+
+    fn simple_type(&mut self) -> Result<Type, String> {
+        match self.scan.cur_token.token_type {
+            TokenType::Integer => {
+                self.match_tok(TokenType::Integer)?;
+                Ok(Type::Integer)
+            },
+            TokenType::Boolean => {
+                self.match_tok(TokenType::Boolean)?;
+                Ok(Type::Boolean)
+            },
+            TokenType::Char => {
+                self.match_tok(TokenType::Char)?;
+                Ok(Type::Char)
+            },
+            TokenType::Real => {
+                self.match_tok(TokenType::Real)?;
+                Ok(Type::Real)
+            },
+            _ => Err(parser_error("TK_INTEGER, TK_BOOLEAN, TK_CHAR or TK_REAL", self.scan.cur_token.clone())),
+        }
+    }
+
+    // Parameters and function results are declared with a type name, rather than a new type
+    fn param_type(&mut self) -> Result<Type, String> {
+        let tok = self.scan.cur_token.clone();
+        if tok.token_type == TokenType::AType {
+            self.match_tok(TokenType::AType)?;
+            return Ok(tok.dtype.expect("Type name should have a type"));
+        }
+        self.simple_type()
+    }
+
+    fn type_spec(&mut self) -> Result<Type, String> {
+        match self.scan.cur_token.token_type {
+            TokenType::Array => self.array_type(),
+            TokenType::Record => self.record_type(),
+            TokenType::Set => self.set_type(),
+            TokenType::Caret => self.pointer_type(),
+            TokenType::LParen => self.enum_type(),
+            TokenType::AType | TokenType::Integer | TokenType::Boolean | TokenType::Char | TokenType::Real => {
+                self.param_type()
+            },
+            TokenType::IntLit | TokenType::CharLit | TokenType::BoolLit | TokenType::AConst | TokenType::OpMinus | TokenType::OpPlus |
+            TokenType::Ord | TokenType::Chr | TokenType::Succ | TokenType::Pred => {
+                self.subrange_type()
+            },
+            _ => {
+                let errmsg = parser_error("TK_INTEGER, TK_BOOLEAN, TK_CHAR, TK_REAL, TK_A_TYPE, TK_LPAREN, a constant, TK_SET, TK_CARET, TK_ARRAY or TK_RECORD", self.scan.cur_token.clone());
+                Err(errmsg)
+            }
+        }
+    }
+
+    fn array_type(&mut self) -> Result<Type, String> {
+        self.match_tok(TokenType::Array)?;
+        self.match_tok(TokenType::LBrack)?;
+        self.array_ranges()
+    }
+
+    // An array with several index types is an array of arrays, one index type per level
+    fn array_ranges(&mut self) -> Result<Type, String> {
+        // The index may be of any ordinal type short of integer itself, and is given either as a
+        // type or as the bounds of a subrange
+        let tok = self.scan.cur_token.clone();
+        let index_type = self.type_spec()?;
+        let (low, high) = match self.types.bounds(index_type) {
+            Some(bounds) if index_type != Type::Integer => bounds,
+            _ => {
+                let msg = format!("Cannot index an array by {}", self.types.name(index_type));
+                return Err(semantic_error(&msg, &tok))
+            }
+        };
+        let index = self.types.base(index_type);
+
+        let elem = if self.check_tok(TokenType::Comma).is_ok() {
+            self.match_tok(TokenType::Comma)?;
+            self.array_ranges()?
+        } else {
+            self.match_tok(TokenType::RBrack)?;
+            self.match_tok(TokenType::Of)?;
+            self.type_spec()?
+        };
+        if self.types.array_size(low, high, elem).is_none() {
+            return Err(semantic_error("Type too large", &tok))
+        }
+
+        Ok(self.types.add_array(ArrayDef { name: None, index, low, high, elem }))
+    }
+
+    fn set_type(&mut self) -> Result<Type, String> {
+        self.match_tok(TokenType::Set)?;
+        self.match_tok(TokenType::Of)?;
+        let tok = self.scan.cur_token.clone();
+        let elem = self.type_spec()?;
+        self.check_set_elem(elem, &tok)?;
+        Ok(self.types.add_set(SetDef { name: None, elem: Some(elem) }))
+    }
+
+    // A pointer may name a type that is only declared further on, so that linked structures can
+    // point to themselves
+    fn pointer_type(&mut self) -> Result<Type, String> {
+        self.match_tok(TokenType::Caret)?;
+        let tok = self.scan.cur_token.clone();
+        if tok.token_type == TokenType::Ident {
+            self.match_tok(TokenType::Ident)?;
+            let dtype = self.types.add_pointer(PointerDef { name: None, target: None });
+            if let Type::Pointer(id) = dtype {
+                self.pending_pointers.push((id, tok));
+            }
+            return Ok(dtype)
+        }
+
+        let target = self.param_type()?;
+        Ok(self.types.add_pointer(PointerDef { name: None, target: Some(target) }))
+    }
+
+    fn resolve_pointers(&mut self) -> Result<(), String> {
+        for (id, tok) in std::mem::take(&mut self.pending_pointers) {
+            let decl = self.scan.symbol_table.get(tok.clone());
+            match (decl.token_type, decl.dtype) {
+                (TokenType::AType, Some(target)) => self.types.set_target(id, target),
+                _ => return Err(semantic_error("Undefined type", &tok)),
+            }
+        }
+        Ok(())
+    }
+
+    // Sets can only hold the values of ordinal types whose ordinals all lie from 0 to 255
+    fn check_set_elem(&self, elem: Type, tok: &Token) -> Result<(), String> {
+        match self.types.bounds(elem) {
+            Some((low, high)) if low >= 0 && high <= 255 => Ok(()),
+            _ => {
+                let msg = format!("Cannot make a set of {}", self.types.name(elem));
+                Err(semantic_error(&msg, tok))
+            }
+        }
+    }
+
+    // The values of an enumeration are declared as constants, numbered from zero
+    fn enum_type(&mut self) -> Result<Type, String> {
+        self.match_tok(TokenType::LParen)?;
+        let names = self.namelist()?;
+        self.match_tok(TokenType::RParen)?;
+
+        let values = names.iter().map(|tok| tok.token_value.clone()).collect();
+        let dtype = self.types.add_enum(EnumDef { name: None, values });
+        let Type::Enum(id) = dtype else { unreachable!() };
+
+        for (ordinal, mut tok) in names.into_iter().enumerate() {
+            tok.token_type = TokenType::AConst;
+            tok.dtype = Some(dtype);
+            tok.value = Some(Value::Enum(id, ordinal as i32));
+            self.declare(tok)?;
+        }
+        Ok(dtype)
+    }
+
+    fn subrange_type(&mut self) -> Result<Type, String> {
+        let lo_tok = self.scan.cur_token.clone();
+        let (base, low) = self.ordinal_const()?;
+        self.match_tok(TokenType::Range)?;
+        let hi_tok = self.scan.cur_token.clone();
+        let (hi_type, high) = self.ordinal_const()?;
+        self.check_type(hi_type, base, &hi_tok)?;
+
+        if high < low {
+            return Err(semantic_error("Upper bound is below the lower bound", &lo_tok));
+        }
+        Ok(self.types.add_subrange(SubrangeDef { name: None, base, low, high }))
+    }
+
+    // Fields are laid out one after the other, in order of declaration
+    fn record_type(&mut self) -> Result<Type, String> {
+        self.match_tok(TokenType::Record)?;
+
+        let mut fields: Vec<Field> = vec![];
+        let mut offset: u32 = 0;
+        while self.check_name() {
+            let names = self.namelist()?;
+            self.match_tok(TokenType::Colon)?;
+            let dtype = self.type_spec()?;
+
+            for tok in names {
+                if fields.iter().any(|f| f.name.eq_ignore_ascii_case(&tok.token_value)) {
+                    return Err(semantic_error("Duplicate field name", &tok));
+                }
+                let next = offset.checked_add(self.types.size(dtype));
+                let Some(next) = next else {
+                    return Err(semantic_error("Type too large", &tok))
+                };
+                fields.push(Field { name: tok.token_value, dtype, offset });
+                offset = next;
+            }
+
+            if self.check_tok(TokenType::Semi).is_err() {
+                break;
+            }
+            self.match_tok(TokenType::Semi)?;
+        }
+        self.match_tok(TokenType::End)?;
+
+        Ok(self.types.add_record(RecordDef { name: None, fields }))
+    }
+
+    fn decl_type(&mut self, names: Vec<Token>) -> Result<(), String> {
+        let dtype = self.type_spec()?;
+        let size = self.types.size(dtype);
+
+        for mut var in names {
+            var.token_type = TokenType::AVar;
+            var.dtype = Some(dtype);
+            self.alloc_var(var, size)?;
+        }
+
+        Ok(())
+    }
+
+    fn int_lit(&mut self) -> Result<i32, String> {
+        let tok = self.scan.cur_token.clone();
+        self.match_tok(TokenType::IntLit)?;
+
+        match tok.token_value.parse::<i32>() {
+            Ok(value) => Ok(value),
+            Err(_e) => Err(semantic_error("Integer literal out of range", &tok)),
+        }
+    }
+
+    // Parse a constant expression of an ordinal type, returning its type and value
+    fn ordinal_const(&mut self) -> Result<(Type, i32), String> {
+        let tok = self.scan.cur_token.clone();
+        let value = self.const_expression()?;
+        match value.ordinal() {
+            Some(ordinal) => Ok((value.dtype(), ordinal)),
+            None => {
+                let msg = format!("Expected an ordinal constant but found {}", value.dtype());
+                Err(semantic_error(&msg, &tok))
+            }
+        }
+    }
+
+    // Give a variable storage in the current procedure's frame, or in the DATA segment if it is global
+    fn alloc_var(&mut self, mut var: Token, size: u32) -> Result<(), String> {
+        self.alloc(&mut var, size)?;
+        self.declare(var)
+    }
+
+    // Reserve storage for a variable in the current frame, or in the DATA segment at global scope.
+    fn alloc(&mut self, var: &mut Token, size: u32) -> Result<(), String> {
+        var.level = self.scan.symbol_table.level();
+        match self.frame_size {
+            Some(used) => {
+                let end = used.checked_add(size).filter(|&end| end <= FRAME_SIZE);
+                let Some(end) = end else {
+                    return Err(semantic_error("Local variables do not fit in a stack frame", var))
+                };
+                var.frame_offset = Some(1 + used as i32);
+                self.frame_size = Some(end);
+            },
+            None => {
+                let end = self.gen.data_addr.checked_add(size).filter(|&end| end <= DATA_SIZE);
+                let Some(end) = end else {
+                    return Err(semantic_error("Global variables do not fit in the DATA segment", var))
+                };
+                var.token_addr = Some(self.gen.data_addr);
+                self.gen.data_addr = end;
+            }
+        }
+        Ok(())
+    }
+
+    fn decl_tail(&mut self) -> Result<(), String> {
+        if self.scan.cur_token.token_type == TokenType::Semi {
+            self.match_tok(TokenType::Semi)?;
+            self.decls()?;
+        }
+        Ok(())
+    }
+
+    fn body(&mut self) -> Result<(), String> {
+        self.begin_st()?;
+        Ok(())
+    }
+
+    fn stats(&mut self) -> Result<(), String> {
+        while
+        self.check_tok(TokenType::AVar).is_ok() ||
+        self.check_tok(TokenType::AConst).is_ok() ||
+        self.check_tok(TokenType::AProcedure).is_ok() ||
+        self.check_tok(TokenType::AFunction).is_ok() ||
+        self.check_tok(TokenType::Repeat).is_ok() ||
+        self.check_tok(TokenType::While).is_ok() ||
+        self.check_tok(TokenType::For).is_ok() ||
+        self.check_tok(TokenType::Begin).is_ok() ||
+        self.check_tok(TokenType::If).is_ok() ||
+        self.check_tok(TokenType::Case).is_ok() ||
+        self.check_tok(TokenType::Goto).is_ok() ||
+        self.check_tok(TokenType::IntLit).is_ok() ||
+        self.check_tok(TokenType::With).is_ok() ||
+        self.check_tok(TokenType::New).is_ok() ||
+        self.check_tok(TokenType::Dispose).is_ok() ||
+        self.check_tok(TokenType::Write).is_ok() ||
+        self.check_tok(TokenType::Writeln).is_ok() ||
+        self.check_tok(TokenType::Read).is_ok() ||
+        self.check_tok(TokenType::Readln).is_ok() ||
+        self.with_field(&self.scan.cur_token).is_some()
+        {
+            self.statement()?;
+            self.stats_tail()?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let labeled = self.check_tok(TokenType::IntLit).is_ok();
+        if labeled {
+            self.label_def()?;
+        }
+
+        // Statements nested within this one belong to a block of their own
+        self.block_count += 1;
+        self.blocks.push(self.block_count);
+        self.statement_body(labeled)?;
+        self.blocks.pop();
+        Ok(())
+    }
+
+    fn statement_body(&mut self, labeled: bool) -> Result<(), String> {
+        // Fields of the records opened by with statements hide any other meaning of their names
+        if self.with_field(&self.scan.cur_token).is_some() {
+            return self.assign_st();
+        }
+
+        match self.scan.cur_token.token_type {
+            TokenType::AVar => self.assign_st()?,
+            TokenType::AConst => {
+                return Err(semantic_error("Cannot assign to a constant", &self.scan.cur_token))
+            },
+            TokenType::AFunction => self.assign_st()?,
+            TokenType::AProcedure => self.procedure_st()?,
+            TokenType::Repeat => self.repeat_st()?,
+            TokenType::While => self.while_st()?,
+            TokenType::For => self.for_st()?,
+            TokenType::Begin => self.begin_st()?,
+            TokenType::If => self.if_st()?,
+            TokenType::Case => self.case_st()?,
+            TokenType::Goto => self.goto_st()?,
+            TokenType::With => self.with_st()?,
+            TokenType::New => self.new_st()?,
+            TokenType::Dispose => self.dispose_st()?,
+            TokenType::Write | TokenType::Writeln => self.write_st()?,
+            TokenType::Read | TokenType::Readln => self.read_st()?,
+            // A label may be put on an empty statement
+            _ if labeled => {},
+            _ => {
+                let errmsg = parser_error("a statement", self.scan.cur_token.clone());
+                return Err(errmsg)
+            }
+        }
+        Ok(())
+    }
+
+    fn stats_tail(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Semi)?;
+        self.stats()?;
+        Ok(())
+    }
+
+    // === STATEMENT HANDLERS =====================================================================
+    fn begin_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Begin)?;
+        self.stats()?;
+        self.match_tok(TokenType::End)?;
+        Ok(())
+    }
+
+    fn assign_st(&mut self) -> Result<(), String> {
+        if self.with_field(&self.scan.cur_token).is_none() {
+            self.check_not_for_var(&self.scan.cur_token)?;
+        }
+        let var_type = self.variable_addr()?;
+        let tok = self.scan.cur_token.clone();
+        self.match_tok(TokenType::OpAssign)?;
+
+        let expr_type = self.expression()?;
+        self.coerce(expr_type, var_type, &tok)?;
+
+        self.store(var_type);
+        Ok(())
+    }
+
+    fn procedure_st(&mut self) -> Result<(), String> {
+        let proc_tok = self.scan.cur_token.clone();    // Copy this for later
+        self.match_tok(TokenType::AProcedure)?;
+        self.call(&proc_tok)?;
+
+        Ok(())
+    }
+
+    fn repeat_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Repeat)?;
+        let label = self.gen.i_ptr;
+
+        self.stats()?;
+        self.match_tok(TokenType::Until)?;
+        self.condition()?;
+
+        // Loop back to the top of the body until the condition becomes true
+        self.gen.op("OP_PUSH");
+        self.gen.data(label.to_string(), "u32", 4);
+        self.gen.op("OP_JFALSE");
+
+        Ok(())
+    }
+
+    fn while_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::While)?;
+        let label = self.gen.i_ptr;
+
+        self.condition()?;
+        self.match_tok(TokenType::Do)?;
+
+
+        self.gen.op("OP_PUSH");
+        let hole = self.gen.i_ptr;
+        self.gen.data("0".to_string(), "u32", 4);
+        self.gen.op("OP_JFALSE");
+
+        self.begin_st()?;
+
+        self.gen.op("OP_PUSH");
+        self.gen.data(label.to_string(), "u32", 4);
+        self.gen.op("OP_JMP");
+
+        let save = self.gen.i_ptr;
+        self.gen.i_ptr = hole;
+
+        self.gen.fill(save.to_string(), "u32", 4);
+        self.gen.i_ptr = save;
+
+        Ok(())
+    }
+
+    fn for_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::For)?;
+
+        // The control variable has to be an ordinal variable of the routine the loop is in
+        let var_tok = self.scan.cur_token.clone();
+        if var_tok.token_type != TokenType::AVar {
+            return Err(parser_error(TokenType::AVar.to_str(), var_tok));
+        }
+        let decl_type = var_tok.dtype.expect("Variable should have a type");
+        let var_type = self.types.base(decl_type);
+        if !var_type.is_ordinal() {
+            return Err(semantic_error("For loop control variable must be an ordinal", &var_tok));
+        }
+        if var_tok.level != self.scan.symbol_table.level() || var_tok.by_ref {
+            return Err(semantic_error("For loop control variable must be local", &var_tok));
+        }
+        self.check_not_for_var(&var_tok)?;
+
+        // The final value is evaluated once, into a hidden variable
+        let mut last = Token::new(TokenType::AVar, "for".to_string(), var_tok.src_info);
+        self.alloc(&mut last, 4)?;
+
+        // Evaluate both bounds before assigning either of them. Both have to lie within a subrange
+        // variable's bounds, as every value in between is stored in it.
+        self.variable_addr()?;
+        let tok = self.scan.cur_token.clone();
+        self.match_tok(TokenType::OpAssign)?;
+        let first_type = self.expression()?;
+        self.coerce(first_type, decl_type, &tok)?;
+
+        let down = self.check_tok(TokenType::Downto).is_ok();
+        let tok = self.scan.cur_token.clone();
+        if down {
+            self.match_tok(TokenType::Downto)?;
+        } else {
+            self.match_tok(TokenType::To)?;
+        }
+        self.push_var_addr(&last);
+        let last_type = self.expression()?;
+        self.coerce(last_type, decl_type, &tok)?;
+        self.gen.op("OP_STORE");
+        self.gen.op("OP_STORE");
+
+        self.match_tok(TokenType::Do)?;
+
+        // Skip the loop entirely when the range is empty
+        self.push_var_addr(&var_tok);
+        self.gen.op("OP_LOAD");
+        self.push_var_addr(&last);
+        self.gen.op("OP_LOAD");
+        self.gen.op(if down { "OP_GTE" } else { "OP_LTE" });
+        self.gen.op("OP_PUSH");
+        let hole = self.gen.i_ptr;
+        self.gen.data("0".to_string(), "u32", 4);
+        self.gen.op("OP_JFALSE");
+
+        let label = self.gen.i_ptr;
+        self.for_vars.push(var_tok.clone());
+        self.statement()?;
+        self.for_vars.pop();
+
+        // Stop once the body has run for the final value, so the control variable is left
+        // holding it and never steps past the end of its type. A nested routine could still have
+        // moved the variable past the final value, which stops the loop as well.
+        self.push_var_addr(&var_tok);
+        self.gen.op("OP_LOAD");
+        self.push_var_addr(&last);
+        self.gen.op("OP_LOAD");
+        self.gen.op(if down { "OP_LTE" } else { "OP_GTE" });
+        self.gen.op("OP_PUSH");
+        let hole2 = self.gen.i_ptr;
+        self.gen.data("0".to_string(), "u32", 4);
+        self.gen.op("OP_JTRUE");
+
+        self.push_var_addr(&var_tok);
+        self.push_var_addr(&var_tok);
+        self.gen.op("OP_LOAD");
+        self.gen.op("OP_PUSH");
+        self.gen.data("1".to_string(), "u32", 4);
+        self.gen.op(if down { "OP_SUB" } else { "OP_ADD" });
+        self.gen.op("OP_STORE");
+
+        self.gen.op("OP_PUSH");
+        self.gen.data(label.to_string(), "u32", 4);
+        self.gen.op("OP_JMP");
+
+        let save = self.gen.i_ptr;
+        for h in [hole, hole2] {
+            self.gen.i_ptr = h;
+            self.gen.fill(save.to_string(), "u32", 4);
+        }
+        self.gen.i_ptr = save;
+
+        Ok(())
+    }
+
+    // Control variables of enclosing for loops may not be changed by their bodies
+    fn check_not_for_var(&self, tok: &Token) -> Result<(), String> {
+        let is_for_var = self.for_vars.iter()
+            .any(|v| v.token_value.eq_ignore_ascii_case(&tok.token_value) && v.level == tok.level);
+        if tok.token_type == TokenType::AVar && is_for_var {
+            return Err(semantic_error("Cannot assign to the control variable of a for loop", tok));
+        }
+        Ok(())
+    }
+
+    fn with_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::With)?;
+
+        // The address of each record is worked out once, before the statement runs
+        let opened = self.withs.len();
+        loop {
+            let tok = self.scan.cur_token.clone();
+            let mut slot = Token::new(TokenType::AVar, "with".to_string(), tok.src_info);
+            self.alloc(&mut slot, 4)?;
+            self.push_var_addr(&slot);
+            let dtype = self.variable_addr()?;
+            if !matches!(dtype, Type::Record(_)) {
+                let msg = format!("Expected a record but found {}", self.types.name(dtype));
+                return Err(semantic_error(&msg, &tok));
+            }
+            self.gen.op("OP_STORE");
+            self.withs.push((slot, dtype));
+
+            if self.check_tok(TokenType::Comma).is_err() {
+                break;
+            }
+            self.match_tok(TokenType::Comma)?;
+        }
+        self.match_tok(TokenType::Do)?;
+
+        self.statement()?;
+        self.withs.truncate(opened);
+        Ok(())
+    }
+
+    // Find the innermost record opened by a with statement that has a field named by the given
+    // token. Only names can refer to fields, never literals that happen to spell one.
+    fn with_field(&self, tok: &Token) -> Option<(Token, Type)> {
+        let is_name = matches!(
+            tok.token_type,
+            TokenType::Ident | TokenType::AVar | TokenType::AConst |
+            TokenType::AType | TokenType::AProcedure | TokenType::AFunction
+        );
+        if !is_name {
+            return None;
+        }
+        self.withs.iter().rev()
+            .find(|(_, dtype)| match *dtype {
+                Type::Record(id) => self.types.record(id).field(&tok.token_value).is_some(),
+                _ => false,
+            })
+            .cloned()
+    }
+
+    fn label_def(&mut self) -> Result<(), String> {
+        let tok = self.scan.cur_token.clone();
+        let name = self.label_name()?;
+        self.match_tok(TokenType::Colon)?;
+
+        let addr = self.gen.i_ptr;
+        let block = self.blocks.last().copied().unwrap_or(0);
+        let label = match self.labels.get_mut(&name) {
+            Some(label) => label,
+            None => return Err(semantic_error("Undeclared label", &tok)),
+        };
+        if label.addr.is_some() {
+            return Err(semantic_error("Label defined more than once", &tok));
+        }
+        label.addr = Some(addr);
+        label.block = block;
+
+        // Jumps from earlier on may only come from within the block holding the label
+        let gotos = std::mem::take(&mut label.gotos);
+        for (hole, blocks, goto_tok) in gotos {
+            if block != 0 && !blocks.contains(&block) {
+                return Err(semantic_error("Cannot jump into a nested statement", &goto_tok));
+            }
+            self.gen.i_ptr = hole;
+            self.gen.fill(addr.to_string(), "u32", 4);
+        }
+        self.gen.i_ptr = addr;
+
+        Ok(())
+    }
+
+    fn goto_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Goto)?;
+        let tok = self.scan.cur_token.clone();
+        let name = self.label_name()?;
+
+        let label = match self.labels.get_mut(&name) {
+            Some(label) => label,
+            None => return Err(semantic_error("Undeclared label", &tok)),
+        };
+        label.used = true;
+
+        self.gen.op("OP_PUSH");
+        match label.addr {
+            Some(addr) => {
+                if label.block != 0 && !self.blocks.contains(&label.block) {
+                    return Err(semantic_error("Cannot jump into a nested statement", &tok));
+                }
+                self.gen.data(addr.to_string(), "u32", 4);
+            },
+            None => {
+                // A jump forwards, which is filled in once the label is reached
+                label.gotos.push((self.gen.i_ptr, self.blocks.clone(), tok));
+                self.gen.data("0".to_string(), "u32", 4);
+            }
+        }
+        self.gen.op("OP_JMP");
+
+        Ok(())
+    }
+
+    fn case_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Case)?;
+
+        // Keep the selector in a hidden variable, so the dispatch can test it as often as it likes
+        let mut selector = Token::new(TokenType::AVar, "case".to_string(), self.scan.cur_token.src_info);
+        self.alloc(&mut selector, 4)?;
+        self.push_var_addr(&selector);
+        let expr_tok = self.scan.cur_token.clone();
+        let dtype = self.expression()?;
+        if !dtype.is_ordinal() {
+            return Err(semantic_error("Case selector must be an ordinal", &expr_tok));
+        }
+        self.gen.op("OP_STORE");
+        self.match_tok(TokenType::Of)?;
+
+        // The arms are compiled first, and the dispatch to them after
+        self.gen.op("OP_PUSH");
+        let dispatch_hole = self.gen.i_ptr;
+        self.gen.data("0".to_string(), "u32", 4);
+        self.gen.op("OP_JMP");
+
+        // Each label is a range of values, along with the address of its arm
+        let mut labels: Vec<(i32, i32, usize)> = vec![];
+        let mut end_holes = vec![];
+        while self.check_tok(TokenType::Else).is_err() &&
+              self.check_tok(TokenType::Otherwise).is_err() &&
+              self.check_tok(TokenType::End).is_err()
+        {
+            let arm = self.gen.i_ptr;
+            loop {
+                let label_tok = self.scan.cur_token.clone();
+                let (label_type, lo) = self.ordinal_const()?;
+                self.check_type(label_type, dtype, &label_tok)?;
+                let mut hi = lo;
+                if self.check_tok(TokenType::Range).is_ok() {
+                    self.match_tok(TokenType::Range)?;
+                    let (hi_type, value) = self.ordinal_const()?;
+                    self.check_type(hi_type, dtype, &label_tok)?;
+                    hi = value;
+                    if hi < lo {
+                        return Err(semantic_error("Case label range is empty", &label_tok));
+                    }
+                }
+
+                if labels.iter().any(|&(l, h, _)| lo <= h && l <= hi) {
+                    return Err(semantic_error("Duplicate case label", &label_tok));
+                }
+                labels.push((lo, hi, arm));
+
+                if self.check_tok(TokenType::Comma).is_err() {
+                    break;
+                }
+                self.match_tok(TokenType::Comma)?;
+            }
+            self.match_tok(TokenType::Colon)?;
+            self.statement()?;
+
+            self.gen.op("OP_PUSH");
+            end_holes.push(self.gen.i_ptr);
+            self.gen.data("0".to_string(), "u32", 4);
+            self.gen.op("OP_JMP");
+
+            if self.check_tok(TokenType::Semi).is_err() {
+                break;
+            }
+            self.match_tok(TokenType::Semi)?;
+        }
+
+        // Without an else part, a selector that matches no label does nothing
+        let mut default = None;
+        if self.check_tok(TokenType::Else).is_ok() || self.check_tok(TokenType::Otherwise).is_ok() {
+            self.match_tok(self.scan.cur_token.token_type)?;
+            default = Some(self.gen.i_ptr);
+            self.stats()?;
+
+            self.gen.op("OP_PUSH");
+            end_holes.push(self.gen.i_ptr);
+            self.gen.data("0".to_string(), "u32", 4);
+            self.gen.op("OP_JMP");
+        }
+        self.match_tok(TokenType::End)?;
+
+        let dispatch = self.gen.i_ptr;
+        self.gen.i_ptr = dispatch_hole;
+        self.gen.fill(dispatch.to_string(), "u32", 4);
+        self.gen.i_ptr = dispatch;
+        self.case_dispatch(&selector, &labels, default, &mut end_holes);
+
+        let end = self.gen.i_ptr;
+        for hole in end_holes {
+            self.gen.i_ptr = hole;
+            self.gen.fill(end.to_string(), "u32", 4);
+        }
+        self.gen.i_ptr = end;
+
+        Ok(())
+    }
+
+    // Jump to the arm whose labels hold the selector's value. Dense labels are dispatched with a
+    // single jump table, while sparse ones are compared against the selector one after another.
+    fn case_dispatch(&mut self, selector: &Token, labels: &[(i32, i32, usize)], default: Option<usize>, end_holes: &mut Vec<usize>) {
+        let values: i64 = labels.iter().map(|&(lo, hi, _)| hi as i64 - lo as i64 + 1).sum();
+        let low = labels.iter().map(|&(lo, _, _)| lo).min().unwrap_or(0);
+        let high = labels.iter().map(|&(_, hi, _)| hi).max().unwrap_or(0);
+        let span = high as i64 - low as i64 + 1;
+
+        // The address to go to when no label matches; without an else part that is the end of
+        // the case statement, which is not known yet.
+        let mut default_addr = |gen: &mut RvmGenerator| match default {
+            Some(addr) => gen.data(addr.to_string(), "u32", 4),
+            None => {
+                end_holes.push(gen.i_ptr);
+                gen.data("0".to_string(), "u32", 4);
+            }
+        };
+
+        if !labels.is_empty() && span <= 256 && span <= 2 * values {
+            self.push_var_addr(selector);
+            self.gen.op("OP_LOAD");
+            self.gen.op("OP_JTABLE");
+            self.gen.data(low.to_string(), "i32", 4);
+            self.gen.data(span.to_string(), "u32", 4);
+            default_addr(&mut self.gen);
+            for value in low as i64..=high as i64 {
+                let value = value as i32;
+                match labels.iter().find(|&&(lo, hi, _)| lo <= value && value <= hi) {
+                    Some(&(_, _, arm)) => self.gen.data(arm.to_string(), "u32", 4),
+                    None => default_addr(&mut self.gen),
+                }
+            }
+            return;
+        }
+
+        for &(lo, hi, arm) in labels {
+            self.push_var_addr(selector);
+            self.gen.op("OP_LOAD");
+            self.gen.op("OP_PUSH");
+            self.gen.data(lo.to_string(), "i32", 4);
+            if lo == hi {
+                self.gen.op("OP_EQUAL");
+            } else {
+                self.gen.op("OP_GTE");
+                self.push_var_addr(selector);
+                self.gen.op("OP_LOAD");
                 self.gen.op("OP_PUSH");
-                let tok_addr = self.scan.symbol_table.get_addr(&tok).expect("ERR: Variable is undeclared!");
-                self.gen.data(tok_addr.to_string(), "u32", 4);
+                self.gen.data(hi.to_string(), "i32", 4);
+                self.gen.op("OP_LTE");
+                self.gen.op("OP_AND");
+            }
+            self.gen.op("OP_PUSH");
+            self.gen.data(arm.to_string(), "u32", 4);
+            self.gen.op("OP_JTRUE");
+        }
+
+        self.gen.op("OP_PUSH");
+        default_addr(&mut self.gen);
+        self.gen.op("OP_JMP");
+    }
+
+    fn if_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::If)?;
+        self.condition()?;
+
+        // Set up the jump with temporary 0 address
+        self.gen.op("OP_PUSH");
+        let hole = self.gen.i_ptr;
+        self.gen.data("0".to_string(), "u32", 4);
+        self.gen.op("OP_JFALSE");
+
+        self.match_tok(TokenType::Then)?;
+        self.branch_st()?;
+
+        // With an else branch, the then branch has to jump over it
+        let mut hole2 = self.gen.i_ptr;
+        if self.check_tok(TokenType::Else).is_ok() {
+            self.gen.op("OP_PUSH");
+            hole2 = self.gen.i_ptr;
+            self.gen.data("0".to_string(), "u32", 4);
+            self.gen.op("OP_JMP");
+        }
+
+        // Now that statements are done, fill in the previous hole.
+        let save = self.gen.i_ptr;
+        self.gen.i_ptr = hole;
+        self.gen.fill(save.to_string(), "u32", 4);
+        self.gen.i_ptr = save;
+
+        if self.check_tok(TokenType::Else).is_ok() {
+            self.match_tok(TokenType::Else)?;
+            self.branch_st()?;
+
+            let save2 = self.gen.i_ptr;
+            self.gen.i_ptr = hole2;
+            self.gen.fill(save2.to_string(), "u32", 4);
+            self.gen.i_ptr = save2;
+        }
+        Ok(())
+    }
+
+    // Each branch of an if statement is a block of its own, so that gotos cannot jump between them
+    fn branch_st(&mut self) -> Result<(), String> {
+        self.block_count += 1;
+        self.blocks.push(self.block_count);
+        self.begin_st()?;
+        self.blocks.pop();
+        Ok(())
+    }
+
+    // Point a pointer variable at a newly allocated block of the size of the type it points to
+    fn new_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::New)?;
+        self.match_tok(TokenType::LParen)?;
+        let tok = self.scan.cur_token.clone();
+        let dtype = self.variable_addr()?;
+        let target = match dtype {
+            Type::Pointer(id) => self.types.pointer(id).target,
+            _ => None,
+        };
+        let Some(target) = target else {
+            let msg = format!("Expected a pointer but found {}", self.types.name(dtype));
+            return Err(semantic_error(&msg, &tok))
+        };
+        self.match_tok(TokenType::RParen)?;
+
+        self.gen.op("OP_ALLOC");
+        self.gen.data(self.types.size(target).to_string(), "u32", 4);
+        self.gen.op("OP_STORE");
+        Ok(())
+    }
+
+    fn dispose_st(&mut self) -> Result<(), String> {
+        self.match_tok(TokenType::Dispose)?;
+        self.match_tok(TokenType::LParen)?;
+        let tok = self.scan.cur_token.clone();
+        let dtype = self.expression()?;
+        if !matches!(dtype, Type::Pointer(_)) {
+            let msg = format!("Expected a pointer but found {}", self.types.name(dtype));
+            return Err(semantic_error(&msg, &tok))
+        }
+        self.match_tok(TokenType::RParen)?;
+
+        self.gen.op("OP_FREE");
+        Ok(())
+    }
+
+    fn write_st(&mut self) -> Result<(), String> {
+        let newline = self.check_tok(TokenType::Writeln).is_ok();
+        self.match_tok(self.scan.cur_token.token_type)?;
+
+        // The argument list is optional for writeln, which then only ends the line
+        if !newline || self.check_tok(TokenType::LParen).is_ok() {
+            self.match_tok(TokenType::LParen)?;
+            loop {
+                self.write_arg()?;
+                if self.check_tok(TokenType::Comma).is_err() {
+                    break;
+                }
+                self.match_tok(TokenType::Comma)?;
+            }
+            self.match_tok(TokenType::RParen)?;
+        }
+
+        if newline {
+            self.gen.op("OP_WRITELN");
+        }
+        Ok(())
+    }
+
+    fn write_arg(&mut self) -> Result<(), String> {
+        let tok = self.scan.cur_token.clone();
+        match self.expression()? {
+            Type::Integer => self.gen.op("OP_WRITE"),
+            Type::Boolean => self.gen.op("OP_WRITEB"),
+            Type::Char => self.gen.op("OP_WRITEC"),
+            Type::Real => self.gen.op("OP_WRITER"),
+            Type::String => self.gen.op("OP_WRITES"),
+            dtype => {
+                let msg = format!("Cannot write a value of type {}", self.types.name(dtype));
+                return Err(semantic_error(&msg, &tok))
+            }
+        }
+        Ok(())
+    }
+
+    fn read_st(&mut self) -> Result<(), String> {
+        let newline = self.check_tok(TokenType::Readln).is_ok();
+        self.match_tok(self.scan.cur_token.token_type)?;
+
+        // The argument list is optional for readln, which then only skips the rest of the line
+        if !newline || self.check_tok(TokenType::LParen).is_ok() {
+            self.match_tok(TokenType::LParen)?;
+            loop {
+                self.read_arg()?;
+                if self.check_tok(TokenType::Comma).is_err() {
+                    break;
+                }
+                self.match_tok(TokenType::Comma)?;
+            }
+            self.match_tok(TokenType::RParen)?;
+        }
+
+        if newline {
+            self.gen.op("OP_READLN");
+        }
+        Ok(())
+    }
+
+    fn read_arg(&mut self) -> Result<(), String> {
+        let tok = self.scan.cur_token.clone();
+        if self.with_field(&tok).is_none() {
+            if tok.token_type != TokenType::AVar {
+                return Err(parser_error(TokenType::AVar.to_str(), tok));
+            }
+            self.check_not_for_var(&tok)?;
+        }
+        let dtype = self.variable_addr()?;
+
+        match self.types.base(dtype) {
+            Type::Integer => self.gen.op("OP_READ"),
+            Type::Char => self.gen.op("OP_READC"),
+            Type::Real => self.gen.op("OP_READR"),
+            // Strings are read into arrays of characters
+            Type::Array(id) if self.types.array(id).elem == Type::Char => {
+                let def = self.types.array(id);
+                let len = def.high - def.low + 1;
+                self.gen.op("OP_READS");
+                self.gen.data(len.to_string(), "u32", 4);
+            },
+            _ => {
+                let msg = format!("Cannot read a value of type {}", self.types.name(dtype));
+                return Err(semantic_error(&msg, &tok))
+            }
+        }
+        if let Type::Subrange(id) = dtype {
+            let def = self.types.subrange(id);
+            let (low, high) = (def.low, def.high);
+            self.check_range(low, high);
+        }
+
+        self.store(dtype);
+        Ok(())
+    }
+
+    // === EXPRESSION PARSERS =====================================================================
+    // Evaluate the condition of an if statement or loop, which has to be a boolean.
+    fn condition(&mut self) -> Result<(), String> {
+        let tok = self.scan.cur_token.clone();
+        let dtype = self.expression()?;
+        self.check_type(dtype, Type::Boolean, &tok)
+    }
+
+    // Relational operators bind the loosest, and do not chain
+    fn expression(&mut self) -> Result<Type, String> {
+        let mut dtype = self.simple_expression()?;
+        if
+        self.check_tok(TokenType::OpEqual).is_ok() ||
+        self.check_tok(TokenType::OpNEqual).is_ok() ||
+        self.check_tok(TokenType::OpLessThan).is_ok() ||
+        self.check_tok(TokenType::OpLessThanEql).is_ok() ||
+        self.check_tok(TokenType::OpGreatThan).is_ok() ||
+        self.check_tok(TokenType::OpGreatThanEql).is_ok() ||
+        self.check_tok(TokenType::OpIn).is_ok()
+        {
+            let tok = self.scan.cur_token.clone();
+            self.match_tok(tok.token_type)?;
+            let right = self.simple_expression()?;
+            dtype = self.binary_op(&tok, dtype, right)?;
+        }
+        Ok(dtype)
+    }
+
+    fn simple_expression(&mut self) -> Result<Type, String> {
+        let mut dtype = self.term()?;
+        while
+        self.check_tok(TokenType::OpPlus).is_ok() ||
+        self.check_tok(TokenType::OpMinus).is_ok() ||
+        self.check_tok(TokenType::OpOr).is_ok()
+        {
+            let tok = self.scan.cur_token.clone();
+            self.match_tok(tok.token_type)?;
+            let right = self.term()?;
+            dtype = self.binary_op(&tok, dtype, right)?;
+        };
+        Ok(dtype)
+    }
 
-                // Add the LOAD operation which takes the top of the stack and puts it into address
-                // at DATA segment.
-                self.gen.op("OP_LOAD");
+    fn term(&mut self) -> Result<Type, String> {
+        let mut dtype = self.factor()?;
+        while
+        self.check_tok(TokenType::OpMult).is_ok() ||
+        self.check_tok(TokenType::OpDivi).is_ok() ||
+        self.check_tok(TokenType::OpIntDiv).is_ok() ||
+        self.check_tok(TokenType::OpMod).is_ok() ||
+        self.check_tok(TokenType::OpAnd).is_ok()
+        {
+            let tok = self.scan.cur_token.clone();
+            self.match_tok(tok.token_type)?;
+            let right = self.factor()?;
+            dtype = self.binary_op(&tok, dtype, right)?;
+        };
 
-                self.match_tok(TokenType::AVar)?;
-            },
-            TokenType::AnArrayVar => {
-                let arr_token = self.scan.cur_token.clone();
-                self.match_tok(TokenType::AnArrayVar)?;
-                self.match_tok(TokenType::LBrack)?;
-                self.expression()?; // Parse the index value
-                self.match_tok(TokenType::RBrack)?;
+        Ok(dtype)
+    }
 
-                // Convert the index value on the stack into an array element address!
-                let lo = arr_token.low.expect("Array should have low value!");
-                if lo != 0 {
-                    self.gen.op("OP_PUSH");
-                    self.gen.data(lo.to_string(), "u32", 4);
-                    self.gen.op("OP_SUB");
+    fn factor(&mut self) -> Result<Type, String> {
+        let tok = self.scan.cur_token.clone();
+        let dtype = match tok.token_type {
+            _ if self.with_field(&tok).is_some() => {
+                let dtype = self.variable_addr()?;
+                self.load(dtype);
+                dtype
+            },
+            TokenType::IntLit => {
+                // Push immediate the integer literal onto the stack.
+                let value = self.int_lit()?;
+                self.gen.op("OP_PUSH");
+                self.gen.data(value.to_string(), "i32", 4);
+                Type::Integer
+            },
+            TokenType::RealLit => {
+                self.match_tok(TokenType::RealLit)?;
+                self.gen.op("OP_PUSH");
+                self.gen.data(tok.token_value.clone(), "f32", 4);
+                Type::Real
+            },
+            TokenType::BoolLit => {
+                // Booleans are stored as 1 for true and 0 for false
+                self.match_tok(TokenType::BoolLit)?;
+                self.gen.op("OP_PUSH");
+                self.gen.data((tok.token_value.eq_ignore_ascii_case("true") as u32).to_string(), "u32", 4);
+                Type::Boolean
+            },
+            TokenType::CharLit => {
+                // Characters are stored as their character code
+                self.match_tok(TokenType::CharLit)?;
+                let code = tok.token_value.chars().next().expect("Character literal should not be empty");
+                self.gen.op("OP_PUSH");
+                self.gen.data((code as u32).to_string(), "u32", 4);
+                Type::Char
+            },
+            TokenType::StrLit => {
+                // Strings are only ever read, so they stay in the constant area after the code
+                self.match_tok(TokenType::StrLit)?;
+                self.gen.op("OP_PUSH");
+                self.gen.str_const(&tok.token_value);
+                Type::String
+            },
+            TokenType::AConst => {
+                self.match_tok(TokenType::AConst)?;
+                let value = tok.value.clone().expect("Constant should have a value");
+                self.push_const(&value);
+                value.dtype()
+            },
+            TokenType::Ord => {
+                // Integers, booleans and characters share a representation, so the conversions
+                // only change the type of the value.
+                self.match_tok(TokenType::Ord)?;
+                self.match_tok(TokenType::LParen)?;
+                let arg_tok = self.scan.cur_token.clone();
+                let dtype = self.expression()?;
+                if !dtype.is_ordinal() {
+                    return Err(semantic_error(&format!("Cannot take the ordinal of {}", dtype), &arg_tok));
+                }
+                self.match_tok(TokenType::RParen)?;
+                Type::Integer
+            },
+            TokenType::Chr => {
+                self.match_tok(TokenType::Chr)?;
+                self.match_tok(TokenType::LParen)?;
+                let arg_tok = self.scan.cur_token.clone();
+                let dtype = self.expression()?;
+                self.check_type(dtype, Type::Integer, &arg_tok)?;
+                self.match_tok(TokenType::RParen)?;
+                Type::Char
+            },
+            TokenType::Succ | TokenType::Pred => {
+                self.match_tok(tok.token_type)?;
+                self.match_tok(TokenType::LParen)?;
+                let arg_tok = self.scan.cur_token.clone();
+                let dtype = self.expression()?;
+                if !dtype.is_ordinal() {
+                    let msg = format!("Cannot apply {} to {}", tok.token_value, self.types.name(dtype));
+                    return Err(semantic_error(&msg, &arg_tok));
                 }
+                self.match_tok(TokenType::RParen)?;
 
                 self.gen.op("OP_PUSH");
-                self.gen.data("4".to_string(), "u32", 4);   // Push the size of array elements
-                self.gen.op("OP_MULT");
+                self.gen.data("1".to_string(), "i32", 4);
+                self.gen.op(if tok.token_type == TokenType::Succ { "OP_ADD" } else { "OP_SUB" });
+
+                // Integers wrap like any other arithmetic, but other types must not step off the
+                // end of their values
+                if dtype != Type::Integer {
+                    let (low, high) = self.types.bounds(dtype).expect("Ordinal should have bounds");
+                    self.check_range(low, high);
+                }
+                dtype
+            },
+            TokenType::AVar => {
+                let dtype = self.variable_addr()?;
+
+                // Add the LOAD operation which takes the address on top of the stack and replaces
+                // it with the value stored there.
+                self.load(dtype);
+                dtype
+            },
+            TokenType::AFunction => {
+                self.match_tok(TokenType::AFunction)?;
 
-                let addr = arr_token.token_addr.expect("Array should have an address by now!");
+                // Reserve the slot for the return value, which is left on top of the stack
+                // once the arguments are popped.
+                self.gen.op("OP_PUSH");
+                self.gen.data("0".to_string(), "u32", 4);
+                self.call(&tok)?;
+                tok.dtype.expect("Function should have a return type")
+            },
+            TokenType::OpPlus => {
+                self.match_tok(TokenType::OpPlus)?;
+                let dtype = self.factor()?;
+                if dtype != Type::Real {
+                    self.check_type(dtype, Type::Integer, &tok)?;
+                }
+                dtype
+            },
+            TokenType::OpMinus => {
+                self.match_tok(TokenType::OpMinus)?;
+                let dtype = self.factor()?;
+                if dtype == Type::Real {
+                    self.gen.op("OP_FNEG");
+                } else {
+                    self.check_type(dtype, Type::Integer, &tok)?;
+                    self.gen.op("OP_NEG");
+                }
+                dtype
+            },
+            TokenType::OpNot => {
+                self.match_tok(TokenType::OpNot)?;
+                let dtype = self.factor()?;
+                self.check_type(dtype, Type::Boolean, &tok)?;
+                self.gen.op("OP_NOT");
+                dtype
+            },
+            TokenType::LParen => {
+                self.match_tok(TokenType::LParen)?;
+                let dtype = self.expression()?;
+                self.match_tok(TokenType::RParen)?;
+                dtype
+            },
+            TokenType::LBrack => self.set_literal()?,
+            TokenType::EofFunc | TokenType::EolnFunc => {
+                self.match_tok(tok.token_type)?;
+                self.gen.op(if tok.token_type == TokenType::EofFunc { "OP_EOF" } else { "OP_EOLN" });
+                Type::Boolean
+            },
+            TokenType::Nil => {
+                self.match_tok(TokenType::Nil)?;
                 self.gen.op("OP_PUSH");
-                self.gen.data(addr.to_string(), "u32", 4); // Pushi the arrays base addr
-                self.gen.op("OP_ADD");
+                self.gen.data("0".to_string(), "u32", 4);
+                self.types.add_pointer(PointerDef { name: None, target: None })
+            },
+            _ => {
+                let errmsg = parser_error("TK_INTLIT, TK_REALLIT, TK_BOOLLIT, TK_CHARLIT, TK_STRLIT, TK_A_CONST, TK_A_VAR, TK_A_FUNCTION, TK_ORD, TK_CHR, TK_SUCC, TK_PRED, TK_PLUS, TK_MINUS, TK_NOT, TK_LPAREN, TK_LBRACK, TK_NIL, TK_EOF_FUNC or TK_EOLN_FUNC", self.scan.cur_token.clone());
+                return Err(errmsg)
+            }
+        };
 
-                self.gen.op("OP_LOAD");
+        // Values of a subrange are values of its base type once loaded
+        Ok(self.types.base(dtype))
+    }
+
+    // Build a set from elements and ranges of elements, starting from an empty set
+    fn set_literal(&mut self) -> Result<Type, String> {
+        self.match_tok(TokenType::LBrack)?;
+        self.gen.op("OP_SEMPTY");
+
+        let mut elem: Option<Type> = None;
+        while self.check_tok(TokenType::RBrack).is_err() {
+            if elem.is_some() {
+                self.match_tok(TokenType::Comma)?;
+            }
+
+            let tok = self.scan.cur_token.clone();
+            let dtype = self.expression()?;
+            match elem {
+                Some(elem) => self.check_type(dtype, elem, &tok)?,
+                // Integers fit as long as the values themselves do, which is checked at runtime
+                None if dtype == Type::Integer => (),
+                None => self.check_set_elem(dtype, &tok)?,
+            }
+            elem = Some(dtype);
+
+            if self.check_tok(TokenType::Range).is_ok() {
+                self.match_tok(TokenType::Range)?;
+                let hi_tok = self.scan.cur_token.clone();
+                let hi_type = self.expression()?;
+                self.check_type(hi_type, dtype, &hi_tok)?;
+                self.gen.op("OP_SRANGE");
+            } else {
+                self.gen.op("OP_SADD");
+            }
+        }
+        self.match_tok(TokenType::RBrack)?;
+
+        Ok(self.types.add_set(SetDef { name: None, elem }))
+    }
+
+    // Check the operand types of a binary operator and emit it, returning the type of its result.
+    // When either operand is a real, the other one is promoted and the real version is used.
+    fn binary_op(&mut self, op: &Token, left: Type, right: Type) -> Result<Type, String> {
+        let numeric = |t| t == Type::Integer || t == Type::Real;
+        let is_set = |t| matches!(t, Type::Set(_));
+
+        // The base type of the elements of the right hand set, for `in`, which any ordinal goes with
+        // when the set is empty
+        let in_elem = match right {
+            Type::Set(id) => self.types.set(id).elem.map_or(left, |elem| self.types.base(elem)),
+            _ => Type::Real,
+        };
+
+        let dtype = match op.token_type {
+            TokenType::OpPlus |
+            TokenType::OpMinus |
+            TokenType::OpMult if is_set(left) && self.types.compatible(left, right) => {
+                self.gen.op(op.to_set_op());
+
+                // The empty set takes on the type of the other set
+                match left {
+                    Type::Set(id) if self.types.set(id).elem.is_none() => right,
+                    _ => left,
+                }
+            },
+            TokenType::OpEqual |
+            TokenType::OpNEqual |
+            TokenType::OpLessThanEql |
+            TokenType::OpGreatThanEql if is_set(left) && self.types.compatible(left, right) => {
+                self.gen.op(op.to_set_op());
+                Type::Boolean
+            },
+            TokenType::OpEqual |
+            TokenType::OpNEqual if matches!(left, Type::Pointer(_)) && self.types.compatible(left, right) => {
+                self.gen.op(op.to_op());
+                Type::Boolean
+            },
+            TokenType::OpIn if left.is_ordinal() && left == in_elem => {
+                self.gen.op(op.to_set_op());
+                Type::Boolean
+            },
+            TokenType::OpPlus |
+            TokenType::OpMinus |
+            TokenType::OpMult |
+            TokenType::OpIntDiv |
+            TokenType::OpMod if left == Type::Integer && right == Type::Integer => {
+                self.gen.op(op.to_op());
+                Type::Integer
+            },
+            TokenType::OpPlus |
+            TokenType::OpMinus |
+            TokenType::OpMult |
+            TokenType::OpDivi if numeric(left) && numeric(right) => {
+                self.promote(left, right);
+                self.gen.op(op.to_real_op());
+                Type::Real
+            },
+            TokenType::OpAnd |
+            TokenType::OpOr if left == Type::Boolean && right == Type::Boolean => {
+                self.gen.op(op.to_op());
+                Type::Boolean
+            },
+            TokenType::OpEqual |
+            TokenType::OpNEqual |
+            TokenType::OpLessThan |
+            TokenType::OpLessThanEql |
+            TokenType::OpGreatThan |
+            TokenType::OpGreatThanEql if left == right && left.is_ordinal() => {
+                self.gen.op(op.to_op());
+                Type::Boolean
+            },
+            TokenType::OpEqual |
+            TokenType::OpNEqual |
+            TokenType::OpLessThan |
+            TokenType::OpLessThanEql |
+            TokenType::OpGreatThan |
+            TokenType::OpGreatThanEql if numeric(left) && numeric(right) => {
+                self.promote(left, right);
+                self.gen.op(op.to_real_op());
+                Type::Boolean
+            },
+            _ => {
+                let msg = format!("Operator cannot be applied to {} and {}", self.types.name(left), self.types.name(right));
+                return Err(semantic_error(&msg, op))
+            }
+        };
+
+        Ok(dtype)
+    }
+
+    // Convert whichever of the two operands on top of the stack is an integer to a real.
+    fn promote(&mut self, left: Type, right: Type) {
+        if right == Type::Integer {
+            self.gen.op("OP_ITOF");
+        }
+        if left == Type::Integer {
+            self.gen.op("OP_ITOF2");
+        }
+    }
+
+    fn push_const(&mut self, value: &Value) {
+        self.gen.op("OP_PUSH");
+        match value {
+            Value::Real(v) => self.gen.data(v.to_string(), "f32", 4),
+            Value::Str(s) => self.gen.str_const(s),
+            _ => {
+                let ordinal = value.ordinal().expect("Constant should be an ordinal");
+                self.gen.data(ordinal.to_string(), "i32", 4);
+            }
+        }
+    }
+
+    // === CONSTANT EXPRESSIONS ===================================================================
+    // These follow the expression parsers above, but work out the value at compile time instead
+    // of emitting code for it.
+    fn const_expression(&mut self) -> Result<Value, String> {
+        let mut value = self.const_simple_expression()?;
+        if
+        self.check_tok(TokenType::OpEqual).is_ok() ||
+        self.check_tok(TokenType::OpNEqual).is_ok() ||
+        self.check_tok(TokenType::OpLessThan).is_ok() ||
+        self.check_tok(TokenType::OpLessThanEql).is_ok() ||
+        self.check_tok(TokenType::OpGreatThan).is_ok() ||
+        self.check_tok(TokenType::OpGreatThanEql).is_ok()
+        {
+            let tok = self.scan.cur_token.clone();
+            self.match_tok(tok.token_type)?;
+            let right = self.const_simple_expression()?;
+            value = self.const_binary(&tok, value, right)?;
+        }
+        Ok(value)
+    }
+
+    fn const_simple_expression(&mut self) -> Result<Value, String> {
+        let mut value = self.const_term()?;
+        while
+        self.check_tok(TokenType::OpPlus).is_ok() ||
+        self.check_tok(TokenType::OpMinus).is_ok() ||
+        self.check_tok(TokenType::OpOr).is_ok()
+        {
+            let tok = self.scan.cur_token.clone();
+            self.match_tok(tok.token_type)?;
+            let right = self.const_term()?;
+            value = self.const_binary(&tok, value, right)?;
+        }
+        Ok(value)
+    }
+
+    fn const_term(&mut self) -> Result<Value, String> {
+        let mut value = self.const_factor()?;
+        while
+        self.check_tok(TokenType::OpMult).is_ok() ||
+        self.check_tok(TokenType::OpDivi).is_ok() ||
+        self.check_tok(TokenType::OpIntDiv).is_ok() ||
+        self.check_tok(TokenType::OpMod).is_ok() ||
+        self.check_tok(TokenType::OpAnd).is_ok()
+        {
+            let tok = self.scan.cur_token.clone();
+            self.match_tok(tok.token_type)?;
+            let right = self.const_factor()?;
+            value = self.const_binary(&tok, value, right)?;
+        }
+        Ok(value)
+    }
+
+    fn const_factor(&mut self) -> Result<Value, String> {
+        let tok = self.scan.cur_token.clone();
+        let value = match tok.token_type {
+            TokenType::IntLit => Value::Integer(self.int_lit()?),
+            TokenType::RealLit => {
+                self.match_tok(TokenType::RealLit)?;
+                Value::Real(tok.token_value.parse::<f32>().expect("Expected f32 conversion"))
+            },
+            TokenType::BoolLit => {
+                self.match_tok(TokenType::BoolLit)?;
+                Value::Boolean(tok.token_value.eq_ignore_ascii_case("true"))
+            },
+            TokenType::CharLit => {
+                self.match_tok(TokenType::CharLit)?;
+                let code = tok.token_value.chars().next().expect("Character literal should not be empty");
+                Value::Char(code as u8)
+            },
+            TokenType::StrLit => {
+                self.match_tok(TokenType::StrLit)?;
+                Value::Str(tok.token_value.clone())
+            },
+            TokenType::AConst => {
+                self.match_tok(TokenType::AConst)?;
+                tok.value.clone().expect("Constant should have a value")
+            },
+            TokenType::Ord | TokenType::Chr | TokenType::Succ | TokenType::Pred => {
+                self.match_tok(tok.token_type)?;
+                self.match_tok(TokenType::LParen)?;
+                let arg_tok = self.scan.cur_token.clone();
+                let arg = self.const_expression()?;
+                self.match_tok(TokenType::RParen)?;
+                let step = match tok.token_type {
+                    TokenType::Succ => 1,
+                    TokenType::Pred => -1,
+                    _ => 0,
+                };
+                let stepped = arg.ordinal()
+                    .and_then(|v| v.checked_add(step))
+                    .filter(|v| self.types.bounds(arg.dtype()).is_some_and(|(low, high)| (low..=high).contains(v)))
+                    .and_then(|v| Value::from_ordinal(arg.dtype(), v));
+                match (tok.token_type, &arg) {
+                    (TokenType::Chr, Value::Integer(code)) if (0..=255).contains(code) => Value::Char(*code as u8),
+                    (TokenType::Ord, _) if arg.ordinal().is_some() => Value::Integer(arg.ordinal().unwrap_or(0)),
+                    (TokenType::Succ | TokenType::Pred, _) if stepped.is_some() => stepped.unwrap_or(arg),
+                    _ => {
+                        let msg = format!("Cannot apply {} to {}", tok.token_value, self.types.name(arg.dtype()));
+                        return Err(semantic_error(&msg, &arg_tok))
+                    }
+                }
+            },
+            TokenType::OpPlus => {
+                self.match_tok(TokenType::OpPlus)?;
+                match self.const_factor()? {
+                    value @ (Value::Integer(_) | Value::Real(_)) => value,
+                    other => {
+                        let msg = format!("Expected integer but found {}", other.dtype());
+                        return Err(semantic_error(&msg, &tok))
+                    }
+                }
             },
             TokenType::OpMinus => {
                 self.match_tok(TokenType::OpMinus)?;
-                self.factor()?;
-                self.gen.op("OP_NEG");
+                match self.const_factor()? {
+                    Value::Integer(v) => Value::Integer(v.checked_neg().ok_or_else(|| semantic_error("Constant out of range", &tok))?),
+                    Value::Real(v) => Value::Real(-v),
+                    other => {
+                        let msg = format!("Expected integer but found {}", other.dtype());
+                        return Err(semantic_error(&msg, &tok))
+                    }
+                }
+            },
+            TokenType::OpNot => {
+                self.match_tok(TokenType::OpNot)?;
+                match self.const_factor()? {
+                    Value::Boolean(v) => Value::Boolean(!v),
+                    other => {
+                        let msg = format!("Expected boolean but found {}", other.dtype());
+                        return Err(semantic_error(&msg, &tok))
+                    }
+                }
             },
             TokenType::LParen => {
                 self.match_tok(TokenType::LParen)?;
-                self.expression()?;
+                let value = self.const_expression()?;
                 self.match_tok(TokenType::RParen)?;
+                value
             },
             _ => {
-                let errmsg = parser_error("TK_INTLIT, TK_IDENT, TK_MINUS, or TK_LPAREN", self.scan.cur_token.clone());
+                let errmsg = parser_error("a constant", tok);
                 return Err(errmsg)
             }
         };
 
+        Ok(value)
+    }
+
+    // Apply a binary operator to two constants, with the same typing rules as `binary_op`
+    fn const_binary(&self, op: &Token, left: Value, right: Value) -> Result<Value, String> {
+        let comparison = matches!(
+            op.token_type,
+            TokenType::OpEqual | TokenType::OpNEqual | TokenType::OpLessThan |
+            TokenType::OpLessThanEql | TokenType::OpGreatThan | TokenType::OpGreatThanEql
+        );
+        let real = op.token_type == TokenType::OpDivi || left.dtype() == Type::Real || right.dtype() == Type::Real;
+        let overflow = || semantic_error("Constant out of range", op);
+
+        let value = match (op.token_type, &left, &right) {
+            (TokenType::OpIntDiv | TokenType::OpMod, Value::Integer(_), Value::Integer(0)) |
+            (TokenType::OpDivi, _, Value::Integer(0)) => return Err(semantic_error("Division by zero", op)),
+            (TokenType::OpPlus, Value::Integer(a), Value::Integer(b)) => Value::Integer(a.checked_add(*b).ok_or_else(overflow)?),
+            (TokenType::OpMinus, Value::Integer(a), Value::Integer(b)) => Value::Integer(a.checked_sub(*b).ok_or_else(overflow)?),
+            (TokenType::OpMult, Value::Integer(a), Value::Integer(b)) => Value::Integer(a.checked_mul(*b).ok_or_else(overflow)?),
+            (TokenType::OpIntDiv, Value::Integer(a), Value::Integer(b)) => Value::Integer(a.checked_div(*b).ok_or_else(overflow)?),
+            (TokenType::OpMod, Value::Integer(a), Value::Integer(b)) => Value::Integer(a.checked_rem(*b).ok_or_else(overflow)?),
+            (TokenType::OpAnd, Value::Boolean(a), Value::Boolean(b)) => Value::Boolean(*a && *b),
+            (TokenType::OpOr, Value::Boolean(a), Value::Boolean(b)) => Value::Boolean(*a || *b),
+            _ => match (left.real(), right.real(), left.ordinal(), right.ordinal()) {
+                // Reals, or an integer mixed with a real
+                (Some(a), Some(b), _, _) if real => match op.token_type {
+                    TokenType::OpPlus => Value::Real(a + b),
+                    TokenType::OpMinus => Value::Real(a - b),
+                    TokenType::OpMult => Value::Real(a * b),
+                    TokenType::OpDivi if b == 0.0 => return Err(semantic_error("Division by zero", op)),
+                    TokenType::OpDivi => Value::Real(a / b),
+                    _ if comparison => Value::Boolean(compare(op.token_type, a.partial_cmp(&b))),
+                    _ => return Err(self.const_type_error(op, &left, &right)),
+                },
+                // Comparisons of ordinals of the same type
+                (_, _, Some(a), Some(b)) if comparison && left.dtype() == right.dtype() => {
+                    Value::Boolean(compare(op.token_type, a.partial_cmp(&b)))
+                },
+                _ => return Err(self.const_type_error(op, &left, &right)),
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn const_type_error(&self, op: &Token, left: &Value, right: &Value) -> String {
+        let msg = format!("Operator cannot be applied to {} and {}", left.dtype(), right.dtype());
+        semantic_error(&msg, op)
+    }
+
+    // === CALLS ==================================================================================
+    // Push the arguments for a call to the given procedure or function, then call it.
+    fn call(&mut self, proc_tok: &Token) -> Result<(), String> {
+        // Push the arguments in order: a value for value parameters, an address for var parameters
+        if !proc_tok.params.is_empty() {
+            self.match_tok(TokenType::LParen)?;
+            for (i, param) in proc_tok.params.iter().enumerate() {
+                if i > 0 {
+                    self.match_tok(TokenType::Comma)?;
+                }
+
+                let arg_tok = self.scan.cur_token.clone();
+                let param_type = param.dtype.expect("Parameter should have a type");
+                if param.by_ref {
+                    // The callee could change a variable passed by reference
+                    if self.with_field(&arg_tok).is_none() {
+                        self.check_not_for_var(&arg_tok)?;
+                    }
+                    let dtype = self.variable_addr()?;
+                    self.check_type(dtype, param_type, &arg_tok)?;
+                } else {
+                    let dtype = self.expression()?;
+                    self.coerce(dtype, param_type, &arg_tok)?;
+                }
+            }
+            self.match_tok(TokenType::RParen)?;
+        }
+
+        // Pass the frame of the routine that encloses the callee as its static link
+        let hops = self.scan.symbol_table.level() - proc_tok.level;
+        self.gen.op("OP_FRAME");
+        self.gen.data(hops.to_string(), "u32", 4);
+
+        self.gen.op("OP_CALL");
+        match proc_tok.token_addr {
+            Some(addr) => self.gen.data(addr.to_string(), "u32", 4),
+            None => {
+                // Declared forward, so the address is filled in once the body is compiled
+                let (_, calls) = self.forwards.get_mut(&SymbTab::key(&proc_tok.token_value)).expect("Process should have address");
+                calls.push(self.gen.i_ptr);
+                self.gen.data("0".to_string(), "u32", 4);
+            }
+        }
+
+        // The caller cleans up its own arguments and the static link after the call returns
+        self.gen.op("OP_POP");
+        self.gen.data((self.params_size(&proc_tok.params) + 4).to_string(), "u32", 4);
+
         Ok(())
     }
+
+    // === VARIABLE ADDRESSING ====================================================================
+    // Push the address of the variable or array element at the current token onto the stack, and
+    // return the type of the value stored there.
+    fn variable_addr(&mut self) -> Result<Type, String> {
+        let var_tok = self.scan.cur_token.clone();    // Copy this for later
+        let mut dtype = match var_tok.token_type {
+            _ if self.with_field(&var_tok).is_some() => {
+                // A field of a record opened by a with statement, whose address was saved earlier
+                let (slot, record) = self.with_field(&var_tok).expect("Field should exist");
+                self.next_token()?;
+                self.push_var_addr(&slot);
+                self.gen.op("OP_LOAD");
+                self.field_addr(record, &var_tok)?
+            },
+            TokenType::AVar => {
+                self.match_tok(TokenType::AVar)?;
+                self.push_var_addr(&var_tok);
+                var_tok.dtype.expect("Variable should have a type")
+            },
+            TokenType::AFunction if var_tok.frame_offset.is_some() => {
+                // Assigning to the function's name within its own body sets the return value
+                self.match_tok(TokenType::AFunction)?;
+                self.push_var_addr(&var_tok);
+                var_tok.dtype.expect("Variable should have a type")
+            },
+            _ => {
+                let errmsg = parser_error("TK_A_VAR", var_tok);
+                return Err(errmsg)
+            }
+        };
+
+        loop {
+            if self.check_tok(TokenType::LBrack).is_ok() {
+                // a[i, j] is short for a[i][j]
+                self.match_tok(TokenType::LBrack)?;
+                dtype = self.element_addr(dtype)?;
+                while self.check_tok(TokenType::Comma).is_ok() {
+                    self.match_tok(TokenType::Comma)?;
+                    dtype = self.element_addr(dtype)?;
+                }
+                self.match_tok(TokenType::RBrack)?;
+            } else if self.check_tok(TokenType::Caret).is_ok() {
+                dtype = self.deref_addr(dtype)?;
+            } else if self.check_tok(TokenType::Dot).is_ok() {
+                self.match_tok(TokenType::Dot)?;
+                let field_tok = self.match_name()?;
+                dtype = self.field_addr(dtype, &field_tok)?;
+            } else {
+                break;
+            }
+        }
+        Ok(dtype)
+    }
+
+    // Turn the address of a pointer on top of the stack into the address it points to, checking at
+    // runtime that it points to a live block
+    fn deref_addr(&mut self, dtype: Type) -> Result<Type, String> {
+        let tok = self.scan.cur_token.clone();
+        self.match_tok(TokenType::Caret)?;
+        let target = match dtype {
+            Type::Pointer(id) => self.types.pointer(id).target,
+            _ => None,
+        };
+        let Some(target) = target else {
+            let msg = format!("Cannot dereference a value of type {}", self.types.name(dtype));
+            return Err(semantic_error(&msg, &tok))
+        };
+
+        self.gen.op("OP_LOAD");
+        self.gen.op("OP_DEREF");
+        Ok(target)
+    }
+
+    // Turn the address of a record on top of the stack into the address of one of its fields
+    fn field_addr(&mut self, dtype: Type, tok: &Token) -> Result<Type, String> {
+        let field = match dtype {
+            Type::Record(id) => self.types.record(id).field(&tok.token_value).map(|f| (f.offset, f.dtype)),
+            _ => {
+                let msg = format!("Cannot select a field of a value of type {}", self.types.name(dtype));
+                return Err(semantic_error(&msg, tok))
+            }
+        };
+
+        let Some((offset, field_type)) = field else {
+            let msg = format!("{} has no field named {}", self.types.name(dtype), tok.token_value);
+            return Err(semantic_error(&msg, tok))
+        };
+
+        if offset != 0 {
+            self.gen.op("OP_PUSH");
+            self.gen.data(offset.to_string(), "u32", 4);
+            self.gen.op("OP_ADD");
+        }
+        Ok(field_type)
+    }
+
+    // Turn the address of an array on top of the stack into the address of one of its elements
+    fn element_addr(&mut self, dtype: Type) -> Result<Type, String> {
+        let tok = self.scan.cur_token.clone();
+        let (index, low, high, elem) = match dtype {
+            Type::Array(id) => {
+                let def = self.types.array(id);
+                (def.index, def.low, def.high, def.elem)
+            },
+            _ => {
+                let msg = format!("Cannot index a value of type {}", self.types.name(dtype));
+                return Err(semantic_error(&msg, &tok))
+            }
+        };
+
+        let index_type = self.expression()?; // Parse the index value
+        self.check_type(index_type, index, &tok)?;
+        self.check_range(low, high);
+
+        // Convert the index value on the stack into an offset from the array's base address
+        if low != 0 {
+            self.gen.op("OP_PUSH");
+            self.gen.data(low.to_string(), "i32", 4);
+            self.gen.op("OP_SUB");
+        }
+
+        self.gen.op("OP_PUSH");
+        self.gen.data(self.types.size(elem).to_string(), "u32", 4);   // Push the size of array elements
+        self.gen.op("OP_MULT");
+        self.gen.op("OP_ADD");
+
+        Ok(elem)
+    }
+
+    // Replace the address on top of the stack with the value of the given type stored there
+    fn load(&mut self, dtype: Type) {
+        let size = self.types.size(dtype);
+        if size == 4 {
+            self.gen.op("OP_LOAD");
+        } else {
+            self.gen.op("OP_LOADN");
+            self.gen.data(size.to_string(), "u32", 4);
+        }
+    }
+
+    // Store the value of the given type on top of the stack at the address right below it
+    fn store(&mut self, dtype: Type) {
+        let size = self.types.size(dtype);
+        if size == 4 {
+            self.gen.op("OP_STORE");
+        } else {
+            self.gen.op("OP_STOREN");
+            self.gen.data(size.to_string(), "u32", 4);
+        }
+    }
+
+    fn push_var_addr(&mut self, var_tok: &Token) {
+        match var_tok.frame_offset {
+            Some(offset) => {
+                // A function's return value lives in the frame of its body, one level deeper
+                // than the function's name.
+                let mut level = var_tok.level;
+                if var_tok.token_type == TokenType::AFunction {
+                    level += 1;
+                }
+
+                let hops = self.scan.symbol_table.level() - level;
+                if hops == 0 {
+                    self.gen.op("OP_LADDR");
+                    self.gen.data(offset.to_string(), "i32", 4);
+                } else {
+                    // Follow the static links out to the frame of the enclosing routine
+                    self.gen.op("OP_FRAME");
+                    self.gen.data(hops.to_string(), "u32", 4);
+                    self.gen.op("OP_PUSH");
+                    self.gen.data(offset.to_string(), "i32", 4);
+                    self.gen.op("OP_ADD");
+                }
+
+                // The slot of a var parameter holds the address of the caller's variable
+                if var_tok.by_ref {
+                    self.gen.op("OP_LOAD");
+                }
+            },
+            None => {
+                let addr = var_tok.token_addr.expect("Variable should have an address by now!");
+                self.gen.op("OP_PUSH");
+                self.gen.data(addr.to_string(), "u32", 4);
+            }
+        }
+    }
+}
+
+// Whether the ordering of two values satisfies a comparison operator
+fn compare(op: TokenType, ordering: Option<std::cmp::Ordering>) -> bool {
+    use std::cmp::Ordering;
+    match (op, ordering) {
+        (_, None) => op == TokenType::OpNEqual,
+        (TokenType::OpEqual, Some(o)) => o == Ordering::Equal,
+        (TokenType::OpNEqual, Some(o)) => o != Ordering::Equal,
+        (TokenType::OpLessThan, Some(o)) => o == Ordering::Less,
+        (TokenType::OpLessThanEql, Some(o)) => o != Ordering::Greater,
+        (TokenType::OpGreatThan, Some(o)) => o == Ordering::Greater,
+        (TokenType::OpGreatThanEql, Some(o)) => o != Ordering::Less,
+        _ => false,
+    }
 }
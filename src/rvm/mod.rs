@@ -3,17 +3,48 @@
 /// Instruction Set:
 ///     0x00 -> OP_EXIT   -  Terminate execution
 ///     0x01 -> OP_PUSH   -  Push 4 bytes onto stack
-///     0x02 -> OP_POP    -  Pop x bytes from stack
+///     0x02 -> OP_POP    -  Pop x bytes from stack, where x is the 4 byte operand
 ///     0x03 -> OP_STORE  -  Store: Store value at [sp] into DATA address given by [sp - 1]
 ///     0x04 -> OP_LOAD   -  Load: Push value from DATA[stack[sp]] onto stack.
 ///     0x05 -> OP_CALL   -  Call a procedure and push the return address onto the stack.
 ///     0x06 -> OP_RETURN -  Pop the address from the stack and set it to i_ptr.
+///     0x07 -> OP_ENTER  -  Push the frame pointer, point it at the top of stack, and reserve x bytes
+///     0x08 -> OP_LEAVE  -  Drop the current frame and restore the caller's frame pointer
+///     0x09 -> OP_LADDR  -  Push the address of the stack slot at (signed) offset x from the frame pointer
+///     0x0A -> OP_FRAME  -  Push the address of the frame x static links out from the current one
+///     0x0B -> OP_LOADN  -  Load x bytes: Replace the address on top of stack with the x bytes stored there
+///     0x0C -> OP_STOREN -  Store x bytes: Store the top x bytes of stack at the address right below them
+///     0x0D -> OP_CHECK  -  Stop with an error unless the top of stack lies within the (signed) bounds
+///                          given by the two operands, leaving it in place
 ///     0x10 -> OP_ADD    -  Add two values from stack
 ///     0x11 -> OP_SUB    -  Subtract
-///     0x20 -> OP_WRITE  -  Write the top element of stack to stdout
+///     0x12 -> OP_MULT   -  Multiply
+///     0x13 -> OP_DIVI   -  Divide, truncating towards zero
+///     0x14 -> OP_NEG    -  Negate the top of stack
+///     0x15 -> OP_ITOF   -  Convert the integer on top of stack to a real
+///     0x16 -> OP_ITOF2  -  Convert the integer just below the top of stack to a real
+///     0x17 -> OP_MOD    -  Remainder of dividing, with the sign of the dividend
+///     0x20 -> OP_WRITE  -  Write the top element of stack to stdout, as a signed integer
+///     0x21 -> OP_WRITEB -  Write the top element of stack to stdout, as TRUE or FALSE
+///     0x22 -> OP_WRITEC -  Write the top element of stack to stdout, as the character with that code
+///     0x23 -> OP_WRITES -  Write the string constant at the CODE address on top of stack to stdout
+///     0x24 -> OP_WRITELN - Write a newline to stdout
+///     0x25 -> OP_WRITER -  Write the top element of stack to stdout, as a real
+///     0x26 -> OP_READ   -  Read a signed integer from stdin and push it
+///     0x27 -> OP_READC  -  Read a character from stdin and push its code, where the end of a line
+///                          reads as a space
+///     0x28 -> OP_READR  -  Read a real from stdin and push it
+///     0x29 -> OP_READS  -  Read the rest of the line from stdin and push its first x characters,
+///                          padded with spaces, as x words
+///     0x2A -> OP_READLN -  Skip stdin past the end of the current line
+///     0x2B -> OP_EOF    -  Determine if stdin has no more characters
+///     0x2C -> OP_EOLN   -  Determine if stdin is at the end of a line (or has no more characters)
 ///     0x30 -> OP_JTRUE  -  Jump to address if top of stack is true.
 ///     0x31 -> OP_JFALSE -  Jump to address if top of stack is false.
 ///     0x32 -> OP_JMP    -  Jump to address.
+///     0x33 -> OP_JTABLE -  Jump through a table of addresses, indexed by the top of stack. The
+///                          operands are the lowest index, the number of entries, the address to
+///                          jump to for an index outside the table, and then the entries.
 ///     0x40 -> OP_EQL    -  Determine if two top stack elements are boolean equal
 ///     0x41 -> OP_NEQL   -  Determine if two top stack elements are not boolean equals
 ///     0x42 -> OP_AND    -  Evaluate boolean AND with top two stack
@@ -22,29 +53,104 @@
 ///     0x45 -> OP_LTE    -  Determine if top stack element is less than or equal to bottom
 ///     0x46 -> OP_GT     -  Determine if top stack element is greater than bottom.
 ///     0x47 -> OP_GTE    -  Determine if top stack element is greater than or equal to bottom
+///     0x48 -> OP_NOT    -  Evaluate boolean NOT of the top of stack
+///     0x50 -> OP_FADD   -  Add two reals from stack
+///     0x51 -> OP_FSUB   -  Subtract reals
+///     0x52 -> OP_FMULT  -  Multiply reals
+///     0x53 -> OP_FDIV   -  Divide reals
+///     0x54 -> OP_FNEG   -  Negate the real on top of stack
+///     0x60..0x67        -  Real comparisons, laid out like the integer ones at 0x40..0x47
+///     0x70 -> OP_SEMPTY -  Push an empty set
+///     0x71 -> OP_SADD   -  Add the element on top of stack to the set below it
+///     0x72 -> OP_SRANGE -  Add the elements from [sp - 1] to [sp] to the set below them
+///     0x73 -> OP_SUNION -  Union of the two sets on top of stack
+///     0x74 -> OP_SDIFF  -  Difference: the elements of the lower set that are not in the top one
+///     0x75 -> OP_SINTER -  Intersection of the two sets on top of stack
+///     0x76 -> OP_SEQL   -  Determine if the two sets on top of stack are equal
+///     0x77 -> OP_SNEQL  -  Determine if the two sets on top of stack are not equal
+///     0x78 -> OP_SLTE   -  Determine if the lower set is a subset of the top one
+///     0x79 -> OP_SGTE   -  Determine if the lower set is a superset of the top one
+///     0x7A -> OP_IN     -  Determine if the element below the set on top of stack is in the set
+///     0x80 -> OP_ALLOC  -  Allocate x bytes on the heap, zeroed, and push their address
+///     0x81 -> OP_FREE   -  Free the heap block whose address is on top of stack
+///     0x82 -> OP_DEREF  -  Stop with an error unless the top of stack is the address of a live heap
+///                          block, leaving it in place
+/// Integer arithmetic and comparisons treat values as signed 32-bit integers, wrapping on
+/// overflow. Reals are stored as 32-bit IEEE floats, so every value still fits a 4 byte word.
 ///
+/// Sets are bitsets of SET_SIZE bytes, holding elements from 0 to 255. Bit (n % 8) of byte (n / 8)
+/// is set when n is in the set.
+///
+/// String constants live in a read-only area after the code, each stored as a 4 byte length
+/// followed by its characters.
+///
+/// The last 4 bytes of the image hold the CODE address of the program's name, which is stored
+/// like a string constant, or 0 when the program has no name.
+///
+/// Addresses used by OP_LOAD and OP_STORE below HEAP_BASE refer to the DATA segment, addresses
+/// from HEAP_BASE up to STACK_BASE refer to the heap, and addresses from STACK_BASE upwards refer
+/// to bytes on the stack (see OP_LADDR). The nil pointer is address 0, which is never a heap block.
+///
+/// The heap hands out blocks first-fit from the blocks freed so far, and otherwise grows by up to
+/// HEAP_SIZE bytes. Every load and store of heap memory has to lie within a block that is still
+/// allocated, so addresses kept from before a dispose (by a with statement, say) cannot be used.
+/// Once a later OP_ALLOC hands the same memory out again, such addresses are valid once more.
+///
+/// Numbers are read from stdin as the characters up to the next whitespace, after skipping any
+/// whitespace (including ends of lines) before them.
+///
+/// A procedure call builds the following stack frame, where fp is the frame pointer:
+///     [fp - 11 - n]..   Arguments, in order of declaration (n is the size of the arguments)
+///     [fp - 11]         Static link: the address of the frame of the lexically enclosing procedure
+///     [fp - 7]          Return address
+///     [fp - 3]          Caller's frame pointer
+///     [fp + 1]..        Reserved bytes
+/// The address of a frame is the address of fp itself.
+///
+use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::errors::runtime_error;
+
+const HEAP_BASE: u32 = 0x4000_0000;
+const HEAP_SIZE: usize = 0x10_0000;
+const STACK_BASE: u32 = 0x8000_0000;
+const STACK_SIZE: usize = 0x10000;
+const SET_SIZE: usize = 32;
 
 pub struct RvmMachine {
+    pub name: Option<String>,
     code: Vec<u8>,
     data: Vec<u8>,
+    heap: RvmHeap,
+    input: RvmInput,
     stack: RvmStack<u8>,
     ip: usize,
-    sp: usize
+    fp: usize
 }
 
 impl RvmMachine {
-    pub fn new(code: Vec<u8>) -> RvmMachine {
+    pub fn new(code: Vec<u8>, data_size: usize) -> RvmMachine {
+        let name = match read_be_u32(&mut &code[code.len() - 4..]) as usize {
+            0 => None,
+            addr => Some(code_str(&code, addr)),
+        };
+
         RvmMachine {
+            name,
             code,
-            data: vec![0; 256],
-            stack: RvmStack::new(0, 256),
+            data: vec![0; data_size],
+            heap: RvmHeap::new(),
+            input: RvmInput::new(),
+            stack: RvmStack::new(0, STACK_SIZE),
             ip: 0,
-            sp: 0
+            fp: 0
         }
     }
 
-    pub fn exec(&mut self) {
+    pub fn exec(&mut self) -> Result<(), String> {
         loop {
             let opcode = self.code[self.ip];
             match opcode {
@@ -56,48 +162,201 @@ impl RvmMachine {
                     self.stack.push(self.code[self.ip + 4]);
                     self.ip += 4;
                 },
-                0x02 => self.sp -= 1,
-                0x03 => self.store(),
-                0x04 => self.load(),
+                0x02 => {
+                    let num = self.operand() as usize;
+                    self.stack.pop(num);
+                    self.ip += 4;
+                },
+                0x03 => self.store()?,
+                0x04 => self.load()?,
                 0x05 => self.do_call(),
                 0x06 => self.do_return(),
-                0x10 => self.do_u32_binary(|a, b| a + b),
-                0x11 => self.do_u32_binary(|a, b| b - a),
-                0x12 => self.do_u32_binary(|a, b| a * b),
-                0x13 => self.do_u32_binary(|a, b| b / a),
+                0x07 => self.do_enter(),
+                0x08 => self.do_leave(),
+                0x09 => self.do_laddr(),
+                0x0A => self.do_frame(),
+                0x0B => self.load_block()?,
+                0x0C => self.store_block()?,
+                0x0D => self.check_range()?,
+                0x10 => self.do_i32_binary(|a, b| b.wrapping_add(a)),
+                0x11 => self.do_i32_binary(|a, b| b.wrapping_sub(a)),
+                0x12 => self.do_i32_binary(|a, b| b.wrapping_mul(a)),
+                0x13 => self.do_divide(|a, b| b.wrapping_div(a))?,
+                0x14 => {
+                    let a = read_be_u32(&mut self.stack.pop(4)) as i32;
+                    self.push_u32(a.wrapping_neg() as u32);
+                },
+                0x17 => self.do_divide(|a, b| b.wrapping_rem(a))?,
+                0x15 => {
+                    let a = read_be_u32(&mut self.stack.pop(4)) as i32;
+                    self.push_u32((a as f32).to_bits());
+                },
+                0x16 => {
+                    let a = read_be_u32(&mut self.stack.pop(4));
+                    let b = read_be_u32(&mut self.stack.pop(4)) as i32;
+                    self.push_u32((b as f32).to_bits());
+                    self.push_u32(a);
+                },
                 0x20 => self.write_top(),
+                0x21 => {
+                    let a = read_be_u32(&mut self.stack.pop(4));
+                    output(if a != 0 { "TRUE" } else { "FALSE" });
+                },
+                0x22 => {
+                    let a = read_be_u32(&mut self.stack.pop(4));
+                    output(&(a as u8 as char).to_string());
+                },
+                0x23 => self.write_str(),
+                0x24 => output("\n"),
+                0x25 => {
+                    let a = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+                    output(&format!("{:?}", a));
+                },
+                0x26 => {
+                    let text = self.input.word().ok_or_else(|| runtime_error("Read past the end of input", self.ip))?;
+                    let value: i32 = text.parse().map_err(|_| runtime_error("Invalid integer in input", self.ip))?;
+                    self.push_u32(value as u32);
+                },
+                0x27 => {
+                    let c = self.input.next().ok_or_else(|| runtime_error("Read past the end of input", self.ip))?;
+                    self.push_u32(if c == b'\n' { b' ' } else { c } as u32);
+                },
+                0x28 => {
+                    let text = self.input.word().ok_or_else(|| runtime_error("Read past the end of input", self.ip))?;
+                    let value: f32 = text.parse().map_err(|_| runtime_error("Invalid real in input", self.ip))?;
+                    self.push_u32(value.to_bits());
+                },
+                0x29 => {
+                    let len = self.operand() as usize;
+                    self.ip += 4;
+                    let mut text = vec![];
+                    while let Some(c) = self.input.peek().filter(|&c| c != b'\n') {
+                        text.push(c);
+                        self.input.next();
+                    }
+                    for i in 0..len {
+                        self.push_u32(*text.get(i).unwrap_or(&b' ') as u32);
+                    }
+                },
+                0x2A => while self.input.next().is_some_and(|c| c != b'\n') {},
+                0x2B => {
+                    let eof = self.input.peek().is_none();
+                    self.push_u32(eof as u32);
+                },
+                0x2C => {
+                    let eoln = self.input.peek().is_none_or(|c| c == b'\n');
+                    self.push_u32(eoln as u32);
+                },
                 0x30 => self.jmps(|v| v != 0, true),
                 0x31 => self.jmps(|v| v == 0, true),
                 0x32 => self.jmps(|_v| true, false),
+                0x33 => self.jump_table(),
                 0x40 => self.do_bool_binary(|a, b| a == b),
                 0x41 => self.do_bool_binary(|a, b| a != b),
                 0x42 => self.do_bool_binary(|a, b| {a != 0 && b != 0}),
                 0x43 => self.do_bool_binary(|a, b| {a != 0 || b != 0}),
-                // TO-DO: Are these comparison operations backwards?
-                0x44 => self.do_bool_binary(|a, b| {a > b}),
-                0x45 => self.do_bool_binary(|a, b| {a >= b}),
-                0x46 => self.do_bool_binary(|a, b| {a < b}),
-                0x47 => self.do_bool_binary(|a, b| {a <= b}),
+                // The top of stack `a` is the right hand operand, so these read backwards.
+                0x44 => self.do_bool_binary(|a, b| {(a as i32) > (b as i32)}),
+                0x45 => self.do_bool_binary(|a, b| {(a as i32) >= (b as i32)}),
+                0x46 => self.do_bool_binary(|a, b| {(a as i32) < (b as i32)}),
+                0x47 => self.do_bool_binary(|a, b| {(a as i32) <= (b as i32)}),
+                0x48 => {
+                    let a = read_be_u32(&mut self.stack.pop(4));
+                    self.push_u32((a == 0) as u32);
+                },
+                0x50 => self.do_f32_binary(|a, b| b + a),
+                0x51 => self.do_f32_binary(|a, b| b - a),
+                0x52 => self.do_f32_binary(|a, b| b * a),
+                0x53 => self.do_f32_divide()?,
+                0x54 => {
+                    let a = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+                    self.push_u32((-a).to_bits());
+                },
+                0x60 => self.do_f32_compare(|a, b| a == b),
+                0x61 => self.do_f32_compare(|a, b| a != b),
+                0x64 => self.do_f32_compare(|a, b| a > b),
+                0x65 => self.do_f32_compare(|a, b| a >= b),
+                0x66 => self.do_f32_compare(|a, b| a < b),
+                0x67 => self.do_f32_compare(|a, b| a <= b),
+                0x70 => self.push_set([0; SET_SIZE]),
+                0x71 => {
+                    let elem = read_be_u32(&mut self.stack.pop(4)) as i32;
+                    self.set_add(elem, elem)?;
+                },
+                0x72 => {
+                    let high = read_be_u32(&mut self.stack.pop(4)) as i32;
+                    let low = read_be_u32(&mut self.stack.pop(4)) as i32;
+                    self.set_add(low, high)?;
+                },
+                0x73 => self.do_set_binary(|a, b| b | a),
+                0x74 => self.do_set_binary(|a, b| b & !a),
+                0x75 => self.do_set_binary(|a, b| b & a),
+                0x76 => self.do_set_compare(|a, b| a == b),
+                0x77 => self.do_set_compare(|a, b| a != b),
+                0x78 => self.do_set_compare(|a, b| b.iter().zip(a).all(|(x, y)| x & !y == 0)),
+                0x79 => self.do_set_compare(|a, b| a.iter().zip(b).all(|(x, y)| x & !y == 0)),
+                0x7A => {
+                    let set = self.pop_set();
+                    let elem = read_be_u32(&mut self.stack.pop(4)) as i32;
+                    let found = (0..256).contains(&elem) && set[elem as usize / 8] & (1 << (elem % 8)) != 0;
+                    self.push_u32(found as u32);
+                },
+                0x80 => {
+                    let size = self.operand() as usize;
+                    self.ip += 4;
+                    let offset = self.heap.alloc(size).ok_or_else(|| runtime_error("Out of heap memory", self.ip))?;
+                    self.push_u32(HEAP_BASE + offset as u32);
+                },
+                0x81 => {
+                    let address = read_be_u32(&mut self.stack.pop(4));
+                    if address == 0 {
+                        return Err(runtime_error("Disposing of a nil pointer", self.ip));
+                    }
+                    if !self.heap.free(address.wrapping_sub(HEAP_BASE) as usize) {
+                        return Err(runtime_error("Disposing of a pointer that was already disposed of", self.ip));
+                    }
+                },
+                0x82 => {
+                    let address = read_be_u32(&mut self.stack.pop(4));
+                    if address == 0 {
+                        return Err(runtime_error("Nil pointer dereference", self.ip));
+                    }
+                    if !self.heap.is_live(address.wrapping_sub(HEAP_BASE) as usize) {
+                        return Err(runtime_error("Dereference of a disposed pointer", self.ip));
+                    }
+                    self.push_u32(address);
+                },
                 _ => {
-                    panic!("Illegal RVM instruction. Dump...\n\n");
+                    return Err(runtime_error("Illegal RVM instruction", self.ip));
                 }
             }
 
+            // Deep (or endless) recursion runs out of stack space for new frames
+            if self.stack.overflowed {
+                return Err(runtime_error("Stack overflow", self.ip));
+            }
+
             self.ip += 1;
         }
 
         self.stack.print(false);
+        Ok(())
     }
 
     fn write_top(&mut self) {
-        let a = read_be_u32(&mut self.stack.pop(4));
-        println!("{}", a);
+        let a = read_be_u32(&mut self.stack.pop(4)) as i32;
+        output(&a.to_string());
     }
 
-    fn do_u32_binary<F>(&mut self, binary_op: F) where
-    F: Fn(u32, u32) -> u32 {
-        let a = read_be_u32(&mut self.stack.pop(4));
-        let b = read_be_u32(&mut self.stack.pop(4));
+    fn write_str(&mut self) {
+        let addr = read_be_u32(&mut self.stack.pop(4)) as usize;
+        output(&code_str(&self.code, addr));
+    }
+
+    fn do_i32_binary<F>(&mut self, binary_op: F) where
+    F: Fn(i32, i32) -> i32 {
+        let a = read_be_u32(&mut self.stack.pop(4)) as i32;
+        let b = read_be_u32(&mut self.stack.pop(4)) as i32;
         let result = binary_op(a, b).to_be_bytes();
 
         self.stack.push(result[0]);
@@ -106,6 +365,43 @@ impl RvmMachine {
         self.stack.push(result[3]);
     }
 
+    fn do_divide<F>(&mut self, divide: F) -> Result<(), String> where
+    F: Fn(i32, i32) -> i32 {
+        let a = read_be_u32(&mut self.stack.pop(4)) as i32;
+        let b = read_be_u32(&mut self.stack.pop(4)) as i32;
+        if a == 0 {
+            return Err(runtime_error("Division by zero", self.ip));
+        }
+
+        self.push_u32(divide(a, b) as u32);
+        Ok(())
+    }
+
+    fn do_f32_binary<F>(&mut self, binary_op: F) where
+    F: Fn(f32, f32) -> f32 {
+        let a = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+        let b = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+        self.push_u32(binary_op(a, b).to_bits());
+    }
+
+    fn do_f32_divide(&mut self) -> Result<(), String> {
+        let a = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+        let b = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+        if a == 0.0 {
+            return Err(runtime_error("Division by zero", self.ip));
+        }
+
+        self.push_u32((b / a).to_bits());
+        Ok(())
+    }
+
+    fn do_f32_compare<F>(&mut self, compare: F) where
+    F: Fn(f32, f32) -> bool {
+        let a = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+        let b = f32::from_bits(read_be_u32(&mut self.stack.pop(4)));
+        self.push_u32(compare(a, b) as u32);
+    }
+
     fn do_bool_binary<F>(&mut self, binary_op: F) where
     F: Fn(u32, u32) -> bool {
         let a = read_be_u32(&mut self.stack.pop(4));
@@ -124,34 +420,94 @@ impl RvmMachine {
         }
     }
 
-    fn store(&mut self) {
+    fn operand(&self) -> u32 {
+        u32::from_be_bytes([
+            self.code[self.ip + 1],
+            self.code[self.ip + 2],
+            self.code[self.ip + 3],
+            self.code[self.ip + 4],
+        ])
+    }
+
+    fn push_u32(&mut self, value: u32) {
+        let bytes = value.to_be_bytes();
+        self.stack.push(bytes[0]);
+        self.stack.push(bytes[1]);
+        self.stack.push(bytes[2]);
+        self.stack.push(bytes[3]);
+    }
+
+    // The memory holding the given address, and the index of the address within it. Accesses of
+    // `size` bytes that run past the end of that memory, or outside of a live heap block, are an
+    // error.
+    fn memory(&mut self, address: u32, size: usize) -> Result<(&mut Vec<u8>, usize), String> {
+        let (mem, addr) = if address >= STACK_BASE {
+            (&mut self.stack.stack, (address - STACK_BASE) as usize)
+        } else if address >= HEAP_BASE {
+            let offset = (address - HEAP_BASE) as usize;
+            if !self.heap.is_within_live(offset, size) {
+                return Err(runtime_error("Dereference of a disposed pointer", self.ip));
+            }
+            (&mut self.heap.memory, offset)
+        } else {
+            (&mut self.data, address as usize)
+        };
+
+        if addr + size > mem.len() {
+            return Err(runtime_error("Memory access out of bounds", self.ip));
+        }
+        Ok((mem, addr))
+    }
+
+    fn store(&mut self) -> Result<(), String> {
         let value = read_be_u32(&mut self.stack.pop(4));
         let val_bytes = value.to_be_bytes();
 
         let address = read_be_u32(&mut self.stack.pop(4));
+        let (mem, addr) = self.memory(address, 4)?;
+
+        mem[addr] = val_bytes[0];
+        mem[addr + 1] = val_bytes[1];
+        mem[addr + 2] = val_bytes[2];
+        mem[addr + 3] = val_bytes[3];
+        Ok(())
+    }
+
+    fn load(&mut self) -> Result<(), String> {
+        let address = read_be_u32(&mut self.stack.pop(4));
+        let (mem, addr) = self.memory(address, 4)?;
+        let value = u32::from_be_bytes([mem[addr], mem[addr + 1], mem[addr + 2], mem[addr + 3]]);
 
-        self.data[address as usize] = val_bytes[0];
-        self.data[(address + 1) as usize] = val_bytes[1];
-        self.data[(address + 2) as usize] = val_bytes[2];
-        self.data[(address + 3) as usize] = val_bytes[3];
+        self.push_u32(value);
+        Ok(())
     }
 
-    fn load(&mut self) {
+    fn load_block(&mut self) -> Result<(), String> {
+        let size = self.operand() as usize;
+        self.ip += 4;
+
         let address = read_be_u32(&mut self.stack.pop(4));
+        let (mem, addr) = self.memory(address, size)?;
+        let block = mem[addr..addr + size].to_vec();
+        for byte in block {
+            self.stack.push(byte);
+        }
+        Ok(())
+    }
+
+    fn store_block(&mut self) -> Result<(), String> {
+        let size = self.operand() as usize;
+        self.ip += 4;
 
-        self.stack.push(self.data[address as usize]);
-        self.stack.push(self.data[(address + 1) as usize]);
-        self.stack.push(self.data[(address + 2) as usize]);
-        self.stack.push(self.data[(address + 3) as usize]);
+        let block = self.stack.pop(size)[..size].to_vec();
+        let address = read_be_u32(&mut self.stack.pop(4));
+        let (mem, addr) = self.memory(address, size)?;
+        mem[addr..addr + size].copy_from_slice(&block);
+        Ok(())
     }
 
     fn do_call(&mut self) {
-        let newip = u32::from_be_bytes([
-            self.code[self.ip + 1],
-            self.code[self.ip + 2],
-            self.code[self.ip + 3],
-            self.code[self.ip + 4],
-        ]);
+        let newip = self.operand();
 
         self.ip += 5;
         let ip = self.ip.to_be_bytes();
@@ -169,33 +525,271 @@ impl RvmMachine {
         self.ip = (address - 1) as usize;
     }
 
+    fn do_enter(&mut self) {
+        let reserve = self.operand() as usize;
+        self.ip += 4;
+
+        self.push_u32(self.fp as u32);
+        self.fp = self.stack.sp;
+
+        for _ in 0..reserve {
+            self.stack.push(0);
+        }
+    }
+
+    fn do_leave(&mut self) {
+        self.stack.sp = self.fp;
+        self.fp = read_be_u32(&mut self.stack.pop(4)) as usize;
+    }
+
+    fn do_laddr(&mut self) {
+        let offset = self.operand() as i32;
+        self.ip += 4;
+
+        // Stack bytes live at stack[1..=sp], so the slot's first byte is its address.
+        let slot = (self.fp as i64 + offset as i64) as u32;
+        self.push_u32(STACK_BASE + slot);
+    }
+
+    fn do_frame(&mut self) {
+        let hops = self.operand();
+        self.ip += 4;
+
+        let mut frame = STACK_BASE + self.fp as u32;
+        for _ in 0..hops {
+            let (mem, addr) = self.memory(frame - 11, 4).expect("Static link should be on the stack");
+            frame = u32::from_be_bytes([mem[addr], mem[addr + 1], mem[addr + 2], mem[addr + 3]]);
+        }
+
+        self.push_u32(frame);
+    }
+
     fn jmps<F>(&mut self, cond: F, check: bool) where
     F: Fn(u32) -> bool {
         let addr = read_be_u32(&mut self.stack.pop(4));
-        let val;
-        if check {
-            val = read_be_u32(&mut self.stack.pop(4));
+        let val = if check {
+            read_be_u32(&mut self.stack.pop(4))
         } else {
-            val = 0
-        }
+            0
+        };
         if cond(val) {
             // Subtract a "1" because after outside match, ip is incremented.
             self.ip = addr as usize - 1;
         }
     }
+
+    fn pop_set(&mut self) -> [u8; SET_SIZE] {
+        self.stack.pop(SET_SIZE)[..SET_SIZE].try_into().expect("Set should be SET_SIZE bytes")
+    }
+
+    fn push_set(&mut self, set: [u8; SET_SIZE]) {
+        for byte in set {
+            self.stack.push(byte);
+        }
+    }
+
+    // Add the elements from low to high to the set on top of stack
+    fn set_add(&mut self, low: i32, high: i32) -> Result<(), String> {
+        let mut set = self.pop_set();
+        for elem in low..=high {
+            if !(0..256).contains(&elem) {
+                return Err(runtime_error("Set element out of range", self.ip));
+            }
+            set[elem as usize / 8] |= 1 << (elem % 8);
+        }
+        self.push_set(set);
+        Ok(())
+    }
+
+    fn do_set_binary<F>(&mut self, binary_op: F) where
+    F: Fn(u8, u8) -> u8 {
+        let a = self.pop_set();
+        let b = self.pop_set();
+        let mut result = [0; SET_SIZE];
+        for i in 0..SET_SIZE {
+            result[i] = binary_op(a[i], b[i]);
+        }
+        self.push_set(result);
+    }
+
+    fn do_set_compare<F>(&mut self, compare: F) where
+    F: Fn(&[u8; SET_SIZE], &[u8; SET_SIZE]) -> bool {
+        let a = self.pop_set();
+        let b = self.pop_set();
+        self.push_u32(compare(&a, &b) as u32);
+    }
+
+    fn check_range(&mut self) -> Result<(), String> {
+        let value = read_be_u32(&mut self.stack.pop(4)) as i32;
+        let low = self.operand() as i32;
+        self.ip += 4;
+        let high = self.operand() as i32;
+        self.ip += 4;
+
+        if value < low || value > high {
+            return Err(runtime_error("Value out of range", self.ip));
+        }
+        self.push_u32(value as u32);
+        Ok(())
+    }
+
+    fn jump_table(&mut self) {
+        let value = read_be_u32(&mut self.stack.pop(4)) as i32;
+        let low = self.operand() as i32;
+        self.ip += 4;
+        let count = self.operand() as i64;
+        self.ip += 4;
+
+        // Entries start after the default address
+        let index = value as i64 - low as i64;
+        if index >= 0 && index < count {
+            self.ip += 4 * (index as usize + 1);
+        }
+
+        let addr = self.operand() as usize;
+        self.ip = addr - 1;
+    }
+}
+
+// Memory for blocks allocated at runtime, addressed by their offset from HEAP_BASE
+struct RvmHeap {
+    memory: Vec<u8>,
+
+    // The offset and size of every block currently allocated, and of every freed block
+    live: BTreeMap<usize, usize>,
+    free: BTreeMap<usize, usize>,
+}
+
+impl RvmHeap {
+    fn new() -> RvmHeap {
+        RvmHeap { memory: vec![], live: BTreeMap::new(), free: BTreeMap::new() }
+    }
+
+    fn alloc(&mut self, size: usize) -> Option<usize> {
+        // Keep blocks word aligned, and give each one a distinct address even when it is empty
+        let size = size.max(1).next_multiple_of(4);
+
+        let fit = self.free.iter().find(|&(_, &free_size)| free_size >= size).map(|(&offset, &free_size)| (offset, free_size));
+        let offset = match fit {
+            Some((offset, free_size)) => {
+                self.free.remove(&offset);
+                if free_size > size {
+                    self.free.insert(offset + size, free_size - size);
+                }
+                self.memory[offset..offset + size].fill(0);
+                offset
+            },
+            None => {
+                let offset = self.memory.len();
+                if offset + size > HEAP_SIZE {
+                    return None;
+                }
+                self.memory.resize(offset + size, 0);
+                offset
+            }
+        };
+
+        self.live.insert(offset, size);
+        Some(offset)
+    }
+
+    // Free the block at the given offset, returning false if no block is allocated there
+    fn free(&mut self, offset: usize) -> bool {
+        let Some(mut size) = self.live.remove(&offset) else {
+            return false;
+        };
+
+        // Merge with the free blocks on either side, so that memory does not fragment
+        let mut offset = offset;
+        if let Some(next_size) = self.free.remove(&(offset + size)) {
+            size += next_size;
+        }
+        if let Some((&prev, &prev_size)) = self.free.range(..offset).next_back() {
+            if prev + prev_size == offset {
+                self.free.remove(&prev);
+                offset = prev;
+                size += prev_size;
+            }
+        }
+        self.free.insert(offset, size);
+        true
+    }
+
+    fn is_live(&self, offset: usize) -> bool {
+        self.live.contains_key(&offset)
+    }
+
+    // Whether the `size` bytes from the given offset lie within a single allocated block
+    fn is_within_live(&self, offset: usize, size: usize) -> bool {
+        match self.live.range(..=offset).next_back() {
+            Some((&start, &block_size)) => offset + size <= start + block_size,
+            None => false,
+        }
+    }
+}
+
+// Characters read from stdin, a line at a time so that programs can prompt before each read
+struct RvmInput {
+    line: Vec<u8>,
+    pos: usize,
+    ended: bool,
+}
+
+impl RvmInput {
+    fn new() -> RvmInput {
+        RvmInput { line: vec![], pos: 0, ended: false }
+    }
+
+    // The next character, without consuming it, or None at the end of input
+    fn peek(&mut self) -> Option<u8> {
+        if self.pos == self.line.len() && !self.ended {
+            self.line.clear();
+            self.pos = 0;
+            std::io::stdin().lock().read_until(b'\n', &mut self.line).expect("Could not read from stdin");
+            if self.line.ends_with(b"\r\n") {
+                self.line.truncate(self.line.len() - 2);
+                self.line.push(b'\n');
+            }
+            self.ended = self.line.is_empty();
+        }
+        self.line.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    // Skip any whitespace, then take the characters up to the next whitespace
+    fn word(&mut self) -> Option<String> {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.next();
+        }
+        let mut text = String::new();
+        while let Some(c) = self.peek().filter(|c| !c.is_ascii_whitespace()) {
+            text.push(c as char);
+            self.next();
+        }
+        if text.is_empty() { None } else { Some(text) }
+    }
 }
 
 #[derive(Clone)]
 struct RvmStack<T> {
     stack: Vec<T>,
-    pub sp: usize
+    pub sp: usize,
+    pub overflowed: bool
 }
 
 impl<T: std::fmt::Debug + Clone + std::fmt::Display> RvmStack<T> {
     pub fn new(val: T, siz: usize) -> RvmStack<T> {
         RvmStack {
             stack: vec![val; siz],
-            sp: 0
+            sp: 0,
+            overflowed: false
         }
     }
 
@@ -214,6 +808,11 @@ impl<T: std::fmt::Debug + Clone + std::fmt::Display> RvmStack<T> {
     }
 
     pub fn push(&mut self, byte: T) {
+        if self.sp + 1 == self.stack.len() {
+            self.overflowed = true;
+            return;
+        }
+
         self.sp += 1;
         self.stack[self.sp] = byte;
     }
@@ -226,6 +825,18 @@ impl<T: std::fmt::Debug + Clone + std::fmt::Display> RvmStack<T> {
     }
 }
 
+// Write program output without a trailing newline, flushing so it shows up straight away.
+fn output(text: &str) {
+    print!("{}", text);
+    std::io::stdout().flush().expect("Could not write to stdout");
+}
+
+// The string constant stored at the given CODE address
+fn code_str(code: &[u8], addr: usize) -> String {
+    let len = read_be_u32(&mut &code[addr..]) as usize;
+    code[addr + 4..addr + 4 + len].iter().map(|&c| c as char).collect()
+}
+
 fn read_be_u32(input: &mut &[u8]) -> u32 {
     let (int_bytes, rest) = input.split_at(std::mem::size_of::<u32>());
     *input = rest;
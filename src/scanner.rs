@@ -6,6 +6,10 @@
 * active Token which will be stored in the `cur_token` field.
 *
 * It exposes a public method to get the next token in the file
+*
+* Comments -- `{ ... }`, `(* ... *)` and `//` to the end of the line -- are skipped like whitespace.
+* A comment starting with `$`, such as `{$R-}`, is a compiler directive instead, and is returned as
+* a token holding the text after the `$`.
 */
 use std::fs::File;
 use std::io::Read;
@@ -18,6 +22,8 @@ use crate::tokens::CharGroup;
 
 use crate::symbtab::SymbTab;
 
+const COMPOUND_SYMBS: [&[u8]; 5] = [b":=", b"<>", b"<=", b">=", b".."];
+
 pub struct Scanner {
     src_code: Vec<u8>,
     src_length: usize,
@@ -37,9 +43,8 @@ fn load_buffer(filename: &str) -> Result<(Vec<u8>, usize), String> {
         Err(_e) => return Err(String::from("Could not open file."))
     };
 
-    let f_length;
-    match open_file.read_to_end(&mut buffer) {
-        Ok(x) => f_length = x,
+    let f_length = match open_file.read_to_end(&mut buffer) {
+        Ok(x) => x,
         Err(_e) => return Err(String::from("Could not read file to buffer."))
     };
 
@@ -78,6 +83,9 @@ impl Scanner {
             None => {
                 let first_char = tokens::get_char_group(self.get_char());
                 let potential_token = match first_char {
+                    // A comment, or a directive
+                    _ if self.comment_close().is_some() => self.get_comment(),
+
                     // A integer or numeric literal
                     CharGroup::DIGIT => self.get_num_lit(),
 
@@ -87,6 +95,9 @@ impl Scanner {
                     // A symbol, operator, ...
                     CharGroup::PUNCT => self.get_symb(),
 
+                    // A quoted character or string literal
+                    CharGroup::QUOTE => self.get_quoted(),
+
                     // Skip character
                     CharGroup::WHITE => {
                         if self.get_char() == 10 || self.get_char() == 13 {
@@ -116,10 +127,7 @@ impl Scanner {
                     }
                 };
 
-                match potential_token {
-                    Ok(token) => token,
-                    Err(e) => return Err(e)
-                }
+                potential_token?
             }
         };
 
@@ -130,6 +138,62 @@ impl Scanner {
         self.src_code[self.scan_ptr]
     }
 
+    fn starts_with(&self, text: &[u8]) -> bool {
+        self.src_code[self.scan_ptr..self.src_length - 1].starts_with(text)
+    }
+
+    // If a comment starts at the current character, the length of its opening and how it ends
+    fn comment_close(&self) -> Option<(usize, &'static [u8])> {
+        if self.starts_with(b"{") {
+            Some((1, b"}"))
+        } else if self.starts_with(b"(*") {
+            Some((2, b"*)"))
+        } else if self.starts_with(b"//") {
+            Some((2, b"\n"))
+        } else {
+            None
+        }
+    }
+
+    fn get_comment(&mut self) -> Result<Token, String> {
+        let (line, cnum) = (self.line_num, self.col_num);
+        let (open_len, close) = self.comment_close().expect("Should be at the start of a comment");
+        self.scan_ptr += open_len;
+        self.col_num += open_len;
+
+        let directive = close != b"\n" && self.starts_with(b"$");
+        let start = self.scan_ptr;
+        while !self.starts_with(close) {
+            if self.scan_ptr >= self.src_length - 1 {
+                // A line comment may run to the end of the file, but the others have to be closed
+                if close == b"\n" {
+                    break;
+                }
+                return Err(scanner_error("Unterminated comment".to_string(), String::new(), line, cnum));
+            }
+
+            if self.get_char() == 10 {
+                self.line_num += 1;
+                self.col_num = 0;
+            }
+            self.scan_ptr += 1;
+            self.col_num += 1;
+        }
+        let text = String::from_utf8_lossy(&self.src_code[start..self.scan_ptr]).into_owned();
+
+        // The newline ending a line comment is left to be skipped as whitespace
+        if close != b"\n" {
+            self.scan_ptr += close.len();
+            self.col_num += close.len();
+        }
+
+        if directive {
+            return Ok(Token::new(TokenType::Directive, text[1..].trim().to_string(), (line, cnum)));
+        }
+        self.get_token()?;
+        Ok(self.cur_token.clone())
+    }
+
     fn check_eof(&self) -> Option<Token> {
         if self.scan_ptr == self.src_length - 1 {
             Some(Token::new(TokenType::Eof, String::from("#"), (self.line_num, self.col_num)))
@@ -147,7 +211,8 @@ impl Scanner {
             let char = self.get_char();
             let char_g = tokens::get_char_group(char);
 
-            let next = self.get_char();
+            // Look ahead one character, so that the `..` in `1..5` is not taken for a decimal point
+            let next = self.src_code[(self.scan_ptr + 1).min(self.src_length - 1)];
             let next_g = tokens::get_char_group(next);
 
             match char_g {
@@ -171,11 +236,10 @@ impl Scanner {
             self.col_num += 1;
         }
 
-        let value_str : String;
-        match String::from_utf8(value) {
-            Ok(vstr) => value_str = vstr,
+        let value_str = match String::from_utf8(value) {
+            Ok(vstr) => vstr,
             Err(_e) => return Err(String::from("A UTF-8 Error Occurred"))
-        }
+        };
 
         Ok(Token::new(ttype, value_str, (self.line_num, cnum)))
     }
@@ -198,38 +262,80 @@ impl Scanner {
             self.col_num += 1;
         }
 
-        let value_str : String;
-        match String::from_utf8(value) {
-            Ok(vstr) => value_str = vstr,
+        let value_str = match String::from_utf8(value) {
+            Ok(vstr) => vstr,
             Err(_e) => return Err(String::from("A UTF-8 Error Occurred"))
-        }
+        };
 
-        let ident_token = match &value_str[..] {
+        // Keywords are recognized in any case
+        let spelling = value_str.clone();
+        let ident_token = match &value_str.to_ascii_lowercase()[..] {
             "begin" => self.make_tok(TokenType::Begin, value_str, cnum),
             "end" => self.make_tok(TokenType::End, value_str, cnum),
+            "const" => self.make_tok(TokenType::Const, value_str, cnum),
+            "type" => self.make_tok(TokenType::Type, value_str, cnum),
             "var" => self.make_tok(TokenType::Var, value_str, cnum),
             "integer" => self.make_tok(TokenType::Integer, value_str, cnum),
+            "boolean" => self.make_tok(TokenType::Boolean, value_str, cnum),
+            "char" => self.make_tok(TokenType::Char, value_str, cnum),
+            "real" => self.make_tok(TokenType::Real, value_str, cnum),
+            "true" => self.make_tok(TokenType::BoolLit, value_str, cnum),
+            "false" => self.make_tok(TokenType::BoolLit, value_str, cnum),
             "array" => self.make_tok(TokenType::Array, value_str, cnum),
+            "record" => self.make_tok(TokenType::Record, value_str, cnum),
+            "with" => self.make_tok(TokenType::With, value_str, cnum),
+            "set" => self.make_tok(TokenType::Set, value_str, cnum),
+            "nil" => self.make_tok(TokenType::Nil, value_str, cnum),
+            "new" => self.make_tok(TokenType::New, value_str, cnum),
+            "dispose" => self.make_tok(TokenType::Dispose, value_str, cnum),
             "procedure" => self.make_tok(TokenType::Procedure, value_str, cnum),
+            "function" => self.make_tok(TokenType::Function, value_str, cnum),
+            "forward" => self.make_tok(TokenType::Forward, value_str, cnum),
             "write" => self.make_tok(TokenType::Write, value_str, cnum),
+            "writeln" => self.make_tok(TokenType::Writeln, value_str, cnum),
+            "read" => self.make_tok(TokenType::Read, value_str, cnum),
+            "readln" => self.make_tok(TokenType::Readln, value_str, cnum),
+            "eof" => self.make_tok(TokenType::EofFunc, value_str, cnum),
+            "eoln" => self.make_tok(TokenType::EolnFunc, value_str, cnum),
+            "ord" => self.make_tok(TokenType::Ord, value_str, cnum),
+            "chr" => self.make_tok(TokenType::Chr, value_str, cnum),
+            "succ" => self.make_tok(TokenType::Succ, value_str, cnum),
+            "pred" => self.make_tok(TokenType::Pred, value_str, cnum),
             "repeat" => self.make_tok(TokenType::Repeat, value_str, cnum),
             "until" => self.make_tok(TokenType::Until, value_str, cnum),
+            "for" => self.make_tok(TokenType::For, value_str, cnum),
+            "to" => self.make_tok(TokenType::To, value_str, cnum),
+            "downto" => self.make_tok(TokenType::Downto, value_str, cnum),
             "while" => self.make_tok(TokenType::While, value_str, cnum),
             "do" => self.make_tok(TokenType::Do, value_str, cnum),
             "of" => self.make_tok(TokenType::Of, value_str, cnum),
             "if" => self.make_tok(TokenType::If, value_str, cnum),
             "then" => self.make_tok(TokenType::Then, value_str, cnum),
             "else" => self.make_tok(TokenType::Else, value_str, cnum),
+            "case" => self.make_tok(TokenType::Case, value_str, cnum),
+            "otherwise" => self.make_tok(TokenType::Otherwise, value_str, cnum),
+            "label" => self.make_tok(TokenType::Label, value_str, cnum),
+            "goto" => self.make_tok(TokenType::Goto, value_str, cnum),
+            "program" => self.make_tok(TokenType::Program, value_str, cnum),
+            "uses" => self.make_tok(TokenType::Uses, value_str, cnum),
             "and" => self.make_tok(TokenType::OpAnd, value_str, cnum),
             "or" => self.make_tok(TokenType::OpOr, value_str, cnum),
+            "div" => self.make_tok(TokenType::OpIntDiv, value_str, cnum),
+            "mod" => self.make_tok(TokenType::OpMod, value_str, cnum),
+            "not" => self.make_tok(TokenType::OpNot, value_str, cnum),
+            "in" => self.make_tok(TokenType::OpIn, value_str, cnum),
             _ => self.make_tok(TokenType::Ident, value_str, cnum),
         };
 
         // Add the scanned token to symbol table. The method will only insert if does not exist yet.
         self.symbol_table.add(ident_token.clone().unwrap());
 
-        // Retrieve the newest version of this token from the symbol table
-        Ok(self.symbol_table.get(ident_token.unwrap()))
+        // Retrieve the newest version of this token from the symbol table, keeping this occurrence's
+        // spelling and position for error messages
+        let mut tok = self.symbol_table.get(ident_token.unwrap());
+        tok.token_value = spelling;
+        tok.src_info = (self.line_num, cnum);
+        Ok(tok)
     }
 
     fn get_symb(&mut self) -> Result<Token, String> {
@@ -240,10 +346,15 @@ impl Scanner {
             let char = self.get_char();
             let char_g = tokens::get_char_group(char);
 
-            if !value.is_empty() && char == 59 {
-                // Never include semicolons in a token being scanned,
-                // unless they appear alone.
-                break;
+            if !value.is_empty() {
+                // Only the compound operators span more than one character, so stop scanning
+                // unless this character completes one of them. This way `a[i]);` is not mistaken
+                // for a single (invalid) symbol.
+                let mut pair = value.clone();
+                pair.push(char);
+                if !COMPOUND_SYMBS.contains(&&pair[..]) {
+                    break;
+                }
             }
 
             match char_g {
@@ -255,9 +366,8 @@ impl Scanner {
             self.col_num += 1;
         };
 
-        let value_str : String;
-        match String::from_utf8(value) {
-            Ok(vstr) => value_str = vstr,
+        let value_str = match String::from_utf8(value) {
+            Ok(vstr) => vstr,
             Err(_e) => return Err(String::from("A UTF-8 Error Occurred"))
         };
 
@@ -282,6 +392,8 @@ impl Scanner {
             "," => self.make_tok(TokenType::Comma, value_str, cnum),
             "." => self.make_tok(TokenType::Dot, value_str, cnum),
             ".." => self.make_tok(TokenType::Range, value_str, cnum),
+            "^" => self.make_tok(TokenType::Caret, value_str, cnum),
+            "#" => self.get_char_code(cnum),
             _ => {
                 let errmsg = scanner_error(
                     "Invalid operator or symbol".to_string(),
@@ -294,6 +406,80 @@ impl Scanner {
         }
     }
 
+    fn get_quoted(&mut self) -> Result<Token, String> {
+        let mut value = vec![];
+        let cnum = self.col_num;
+
+        // Skip the opening quote
+        self.scan_ptr += 1;
+        self.col_num += 1;
+
+        loop {
+            if self.scan_ptr >= self.src_length - 1 || self.get_char() == 10 || self.get_char() == 13 {
+                let errmsg = scanner_error(
+                    "Unterminated string literal".to_string(),
+                    String::from_utf8_lossy(&value).to_string(),
+                    self.line_num,
+                    cnum
+                );
+                return Err(errmsg)
+            }
+
+            let char = self.get_char();
+            self.scan_ptr += 1;
+            self.col_num += 1;
+
+            if char == 39 {
+                // A doubled quote stands for a single quote character
+                if self.get_char() != 39 {
+                    break;
+                }
+                self.scan_ptr += 1;
+                self.col_num += 1;
+            }
+
+            value.push(char);
+        }
+
+        // Characters are stored as single bytes, so the source's UTF-8 has to decode to Latin-1
+        let value_str = String::from_utf8_lossy(&value).to_string();
+        if value_str.chars().any(|c| c > '\u{ff}') {
+            let errmsg = scanner_error(
+                "Character outside of Latin-1 in literal".to_string(),
+                value_str,
+                self.line_num,
+                cnum
+            );
+            return Err(errmsg)
+        }
+
+        // A single quoted character is a char, anything else is a string
+        if value_str.chars().count() == 1 {
+            return self.make_tok(TokenType::CharLit, value_str, cnum)
+        }
+        self.make_tok(TokenType::StrLit, value_str, cnum)
+    }
+
+    // Scan the digits of a `#65` style character code, after the `#` itself.
+    fn get_char_code(&mut self, cnum: usize) -> Result<Token, String> {
+        let code_tok = self.get_num_lit()?;
+
+        match code_tok.token_value.parse::<u8>() {
+            Ok(code) if code_tok.token_type == TokenType::IntLit => {
+                self.make_tok(TokenType::CharLit, (code as char).to_string(), cnum)
+            },
+            _ => {
+                let errmsg = scanner_error(
+                    "Invalid character code".to_string(),
+                    format!("#{}", code_tok.token_value),
+                    self.line_num,
+                    cnum
+                );
+                Err(errmsg)
+            }
+        }
+    }
+
     fn make_tok(&self, tt : TokenType, value: String, col: usize) -> Result<Token, String> {
         Ok(Token::new(tt, value, (self.line_num, col)))
     }
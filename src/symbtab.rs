@@ -2,80 +2,80 @@ use std::collections::HashMap;
 use crate::tokens::Token;
 use crate::tokens::TokenType;
 
+// Names are case-insensitive, so entries are keyed by the lower case form of their names, while
+// the tokens keep the spelling they were declared with.
 #[derive(Debug)]
 pub struct SymbTab {
-    pub table: HashMap<String, Token>
+    pub table: HashMap<String, Token>,
+
+    // Entries shadowed by each open scope, restored once the scope is closed
+    scopes: Vec<Vec<(String, Option<Token>)>>,
 }
 
 impl SymbTab {
     pub fn new(pairs: Vec<Token>) -> SymbTab {
         let mut table = HashMap::new();
         for p in pairs {
-            table.insert(p.token_value.clone(), p);
+            table.insert(SymbTab::key(&p.token_value), p);
         }
 
-        SymbTab { table }
+        SymbTab { table, scopes: vec![] }
     }
 
-    pub fn add(&mut self, tok: Token) {
-        // Add the token to the symbol table only if it doesn't already exist
-        self.table.entry(tok.token_value.clone()).or_insert(tok);
+    pub fn key(name: &str) -> String {
+        name.to_ascii_lowercase()
     }
 
-    pub fn get(&mut self, tok: Token) -> Token {
-        self.table.get(&tok.token_value).expect("Token not found").clone()
+    pub fn level(&self) -> usize {
+        self.scopes.len()
     }
 
-    pub fn set_idents_to(&mut self, ttype: TokenType, starting_addr: u32, dsize: u32) -> u32 {
-        // Return number of idents this function has found.
-        let mut count = 0;
-        let mut addr = starting_addr;
-
-        for token in &mut self.table.clone().values_mut() {
-            if token.token_type == TokenType::Ident {
-                count += 1;
-                let mut new = token.clone();
-                new.token_type = ttype;
-                new.token_addr = Some(addr);
+    pub fn open_scope(&mut self) {
+        self.scopes.push(vec![]);
+    }
 
-                self.set_entry(&token.clone(), &new);
+    pub fn close_scope(&mut self) {
+        let shadowed = self.scopes.pop().expect("No scope to close");
 
-                addr += dsize;
+        for (name, prev) in shadowed.into_iter().rev() {
+            match prev {
+                Some(tok) => { self.table.insert(name, tok); },
+                None => { self.table.remove(&name); },
             }
         }
-
-        count
     }
 
-    pub fn set_idents_to_arr(&mut self, ttype: TokenType, starting_addr: u32, dsize: u32, lo: u32, hi: u32) -> u32 {
-        // Return number of idents this function has found.
-        let mut count = 0;
-        let mut addr = starting_addr;
+    pub fn declare(&mut self, tok: Token) {
+        // Unlike `add`, this replaces any existing entry. Inside a scope, the old entry is kept
+        // around so that it becomes visible again when the scope closes.
+        let name = SymbTab::key(&tok.token_value);
+        let prev = self.table.insert(name.clone(), tok);
 
-        for token in &mut self.table.clone().values_mut() {
-            if token.token_type == TokenType::Ident {
-                count += 1;
-                let mut new = token.clone();
-                new.token_type = ttype;
-                new.token_addr = Some(addr);
-                new.low = Some(lo);
-                new.high = Some(hi);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name, prev));
+        }
+    }
 
-                self.set_entry(&token.clone(), &new);
+    pub fn add(&mut self, tok: Token) {
+        // Add the token to the symbol table only if it doesn't already exist
+        self.table.entry(SymbTab::key(&tok.token_value)).or_insert(tok);
+    }
 
-                addr += dsize;
-            }
-        }
+    pub fn get(&mut self, tok: Token) -> Token {
+        self.table.get(&SymbTab::key(&tok.token_value)).expect("Token not found").clone()
+    }
 
-        count
+    pub fn in_scope(&self, name: &str) -> bool {
+        // Whether the name is already declared in the innermost scope
+        let name = &SymbTab::key(name)[..];
+        match self.scopes.last() {
+            Some(shadowed) => shadowed.iter().any(|(n, _)| n == name),
+            None => self.table.get(name).is_some_and(|tok| tok.token_type != TokenType::Ident),
+        }
     }
 
     pub fn set_entry(&mut self, tok: &Token, new: &Token) {
         let updated = new.clone();
-        *self.table.entry(tok.token_value.clone()).or_insert_with(|| tok.clone()) = updated;
-    }
-
-    pub fn get_addr(&mut self, tok: &Token) -> Option<u32> {
-        self.table[&tok.token_value.clone()].token_addr
+        *self.table.entry(SymbTab::key(&tok.token_value)).or_insert_with(|| tok.clone()) = updated;
     }
 }
@@ -1,5 +1,8 @@
 use std::fmt;
 
+use crate::types::Type;
+use crate::types::Value;
+
 /* Object that represents actual tokens */
 #[derive(Debug, Clone)]
 pub struct Token {
@@ -10,9 +13,21 @@ pub struct Token {
     // For variables
     pub token_addr: Option<u32>,
 
-    // For array variables
-    pub low: Option<u32>,
-    pub high: Option<u32>
+    // The type of a variable, array element, parameter, or function result
+    pub dtype: Option<Type>,
+
+    // For constants
+    pub value: Option<Value>,
+
+    // Nesting depth of the scope the name is declared in, where 0 is the global scope
+    pub level: usize,
+
+    // For parameters and local variables, which live in a stack frame
+    pub frame_offset: Option<i32>,
+    pub by_ref: bool,
+
+    // For procedures
+    pub params: Vec<Token>,
 }
 
 impl Token {
@@ -22,8 +37,12 @@ impl Token {
             token_value,
             src_info,
             token_addr: None,
-            low: None,
-            high: None
+            dtype: None,
+            value: None,
+            level: 0,
+            frame_offset: None,
+            by_ref: false,
+            params: vec![],
         }
     }
 
@@ -34,12 +53,15 @@ impl Token {
             TokenType::OpPlus => "OP_ADD",
             TokenType::OpMinus => "OP_SUB",
             TokenType::OpMult => "OP_MULT",
-            TokenType::OpDivi => "OP_DIVI",
+            TokenType::OpDivi => "OP_FDIV",
+            TokenType::OpIntDiv => "OP_DIVI",
+            TokenType::OpMod => "OP_MOD",
             TokenType::OpAssign => "OP_ASSIGN",
             TokenType::OpEqual => "OP_EQUAL",
             TokenType::OpNEqual => "OP_NEQUAL",
             TokenType::OpAnd => "OP_AND",
             TokenType::OpOr => "OP_OR",
+            TokenType::OpNot => "OP_NOT",
             TokenType::OpLessThan => "OP_LT",
             TokenType::OpLessThanEql => "OP_LTE",
             TokenType::OpGreatThan => "OP_GT",
@@ -48,6 +70,38 @@ impl Token {
             _ => "NOP"
         }
     }
+
+    // The opcode of an arithmetic or comparison operator when applied to reals
+    pub fn to_real_op(&self) -> &str {
+        match self.token_type {
+            TokenType::OpPlus => "OP_FADD",
+            TokenType::OpMinus => "OP_FSUB",
+            TokenType::OpMult => "OP_FMULT",
+            TokenType::OpDivi => "OP_FDIV",
+            TokenType::OpEqual => "OP_FEQUAL",
+            TokenType::OpNEqual => "OP_FNEQUAL",
+            TokenType::OpLessThan => "OP_FLT",
+            TokenType::OpLessThanEql => "OP_FLTE",
+            TokenType::OpGreatThan => "OP_FGT",
+            TokenType::OpGreatThanEql => "OP_FGTE",
+            _ => "NOP"
+        }
+    }
+
+    // Set operators: + is union, - difference, * intersection, and <= and >= test for subsets
+    pub fn to_set_op(&self) -> &str {
+        match self.token_type {
+            TokenType::OpPlus => "OP_SUNION",
+            TokenType::OpMinus => "OP_SDIFF",
+            TokenType::OpMult => "OP_SINTER",
+            TokenType::OpEqual => "OP_SEQL",
+            TokenType::OpNEqual => "OP_SNEQL",
+            TokenType::OpLessThanEql => "OP_SLTE",
+            TokenType::OpGreatThanEql => "OP_SGTE",
+            TokenType::OpIn => "OP_IN",
+            _ => "NOP"
+        }
+    }
 }
 
 /* Names for different types of Tokens */
@@ -59,41 +113,71 @@ pub enum TokenType {
     //** Keywords
     Begin,
     End,
+    Const,
+    Type,
     Var,
+    AConst,
+    AType,
     AVar,
-    AnArrayVar,
     AProcedure,
+    AFunction,
     Integer,
+    Boolean,
+    Char,
+    Real,
     Array,
+    Record,
+    With,
+    Set,
+    Nil,
+    New,
+    Dispose,
     Procedure,
+    Function,
+    Forward,
     Repeat,
     Until,
+    For,
+    To,
+    Downto,
     While,
     Do,
     Of,
     If,
     Then,
     Else,
+    Case,
+    Otherwise,
+    Label,
+    Goto,
+    Program,
+    Uses,
 
     //** Operators
     OpPlus,
     OpMult,
     OpMinus,
     OpDivi,
+    OpIntDiv,
+    OpMod,
     OpAssign,
     OpEqual,
     OpNEqual,
     OpAnd,
     OpOr,
+    OpNot,
     OpLessThan,
     OpLessThanEql,
     OpGreatThan,
     OpGreatThanEql,
+    OpIn,
 
     //** Constants
     IntLit,
     RealLit,
-    // StrLit,
+    BoolLit,
+    CharLit,
+    StrLit,
 
     //** Other
     Ident,
@@ -106,46 +190,88 @@ pub enum TokenType {
     Comma,
     Dot,
     Range,
+    Caret,
+    Directive,
     Write,
+    Writeln,
+    Read,
+    Readln,
+    EofFunc,
+    EolnFunc,
+    Ord,
+    Chr,
+    Succ,
+    Pred,
 }
 
 impl TokenType {
-    pub fn to_str(&self) -> &str {
-        match *self {
+    pub fn to_str(self) -> &'static str {
+        match self {
             TokenType::Null => "TK_NULL",
             TokenType::Eof => "TK_EOF",
             TokenType::Begin => "TK_BEGIN",
             TokenType::End => "TK_END",
+            TokenType::Const => "TK_CONST",
+            TokenType::Type => "TK_TYPE",
             TokenType::Var => "TK_VAR",
+            TokenType::AConst => "TK_A_CONST",
+            TokenType::AType => "TK_A_TYPE",
             TokenType::AVar => "TK_A_VAR",
-            TokenType::AnArrayVar => "TK_AN_ARRAY",
             TokenType::AProcedure => "TK_A_PROCEDURE",
+            TokenType::AFunction => "TK_A_FUNCTION",
             TokenType::Integer => "TK_INTEGER",
+            TokenType::Boolean => "TK_BOOLEAN",
+            TokenType::Char => "TK_CHAR",
+            TokenType::Real => "TK_REAL",
             TokenType::Array => "TK_ARRAY",
+            TokenType::Record => "TK_RECORD",
+            TokenType::With => "TK_WITH",
+            TokenType::Set => "TK_SET",
+            TokenType::Nil => "TK_NIL",
+            TokenType::New => "TK_NEW",
+            TokenType::Dispose => "TK_DISPOSE",
             TokenType::Procedure => "TK_PROCEDURE",
+            TokenType::Function => "TK_FUNCTION",
+            TokenType::Forward => "TK_FORWARD",
             TokenType::Repeat => "TK_REPEAT",
             TokenType::Until => "TK_UNTIL",
+            TokenType::For => "TK_FOR",
+            TokenType::To => "TK_TO",
+            TokenType::Downto => "TK_DOWNTO",
             TokenType::While => "TK_WHILE",
             TokenType::Do => "TK_DO",
             TokenType::Of => "TK_OF",
             TokenType::If => "TK_IF",
             TokenType::Then => "TK_THEN",
             TokenType::Else => "TK_ELSE",
+            TokenType::Case => "TK_CASE",
+            TokenType::Otherwise => "TK_OTHERWISE",
+            TokenType::Label => "TK_LABEL",
+            TokenType::Goto => "TK_GOTO",
+            TokenType::Program => "TK_PROGRAM",
+            TokenType::Uses => "TK_USES",
             TokenType::OpPlus => "TK_PLUS",
             TokenType::OpMinus => "TK_MINUS",
             TokenType::OpMult => "TK_MULT",
             TokenType::OpDivi => "TK_DIVI",
+            TokenType::OpIntDiv => "TK_DIV",
+            TokenType::OpMod => "TK_MOD",
             TokenType::OpAssign => "TK_ASSIGN",
             TokenType::OpEqual => "TK_EQUAL",
             TokenType::OpNEqual => "TK_NOT_EQUAL",
             TokenType::OpAnd => "TK_AND",
             TokenType::OpOr => "TK_OR",
+            TokenType::OpNot => "TK_NOT",
             TokenType::OpLessThan => "TK_LT",
             TokenType::OpLessThanEql => "TK_LTE",
             TokenType::OpGreatThan => "TK_GT",
             TokenType::OpGreatThanEql => "TK_GTE",
+            TokenType::OpIn => "TK_IN",
             TokenType::IntLit => "TK_INTLIT",
             TokenType::RealLit => "TK_REALLIT",
+            TokenType::BoolLit => "TK_BOOLLIT",
+            TokenType::CharLit => "TK_CHARLIT",
+            TokenType::StrLit => "TK_STRLIT",
             TokenType::Ident => "TK_IDENT",
             TokenType::LParen => "TK_LPAREN",
             TokenType::RParen => "TK_RPAREN",
@@ -156,7 +282,18 @@ impl TokenType {
             TokenType::Comma => "TK_COMMA",
             TokenType::Range => "TK_RANGE",
             TokenType::Dot => "TK_DOT",
+            TokenType::Caret => "TK_CARET",
+            TokenType::Directive => "TK_DIRECTIVE",
             TokenType::Write => "TK_WRITE",
+            TokenType::Writeln => "TK_WRITELN",
+            TokenType::Read => "TK_READ",
+            TokenType::Readln => "TK_READLN",
+            TokenType::EofFunc => "TK_EOF_FUNC",
+            TokenType::EolnFunc => "TK_EOLN_FUNC",
+            TokenType::Ord => "TK_ORD",
+            TokenType::Chr => "TK_CHR",
+            TokenType::Succ => "TK_SUCC",
+            TokenType::Pred => "TK_PRED",
         }
     }
 }
@@ -168,6 +305,7 @@ impl fmt::Display for TokenType {
 }
 
 /* Names for different categories of character values the scanner might encounter */
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CharGroup {
     INVLD,
@@ -175,6 +313,7 @@ pub enum CharGroup {
     PUNCT,
     DIGIT,
     ALPHA,
+    QUOTE,
 }
 
 impl fmt::Display for CharGroup {
@@ -185,6 +324,7 @@ impl fmt::Display for CharGroup {
             CharGroup::PUNCT => write!(f, "PUNCT"),
             CharGroup::DIGIT => write!(f, "DIGIT"),
             CharGroup::ALPHA => write!(f, "ALPHA"),
+            CharGroup::QUOTE => write!(f, "QUOTE"),
         }
     }
 }
@@ -231,11 +371,11 @@ pub fn get_char_group(value: u8) -> CharGroup {
         CharGroup::WHITE,  // 32   SPACE
         CharGroup::INVLD,  // 33   !
         CharGroup::INVLD,  // 34   "
-        CharGroup::INVLD,  // 35   #
+        CharGroup::PUNCT,  // 35   #
         CharGroup::INVLD,  // 36   $
         CharGroup::INVLD,  // 37   %
         CharGroup::INVLD,  // 38   &
-        CharGroup::INVLD,  // 39   '
+        CharGroup::QUOTE,  // 39   '
         CharGroup::PUNCT,  // 40   (
         CharGroup::PUNCT,  // 41   )
         CharGroup::PUNCT,  // 42   *
@@ -290,7 +430,7 @@ pub fn get_char_group(value: u8) -> CharGroup {
         CharGroup::PUNCT,  // 91   [
         CharGroup::INVLD,  // 92   \
         CharGroup::PUNCT,  // 93   ]
-        CharGroup::INVLD,  // 94   ^
+        CharGroup::PUNCT,  // 94   ^
         CharGroup::INVLD,  // 95   _
         CharGroup::PUNCT,  // 96   `
         CharGroup::ALPHA,  // 97   a
@@ -319,9 +459,9 @@ pub fn get_char_group(value: u8) -> CharGroup {
         CharGroup::ALPHA,  // 120  x
         CharGroup::ALPHA,  // 121  y
         CharGroup::ALPHA,  // 122  z
-        CharGroup::INVLD,  // 123  {
+        CharGroup::PUNCT,  // 123  {
         CharGroup::INVLD,  // 124  |
-        CharGroup::INVLD,  // 125  }
+        CharGroup::PUNCT,  // 125  }
         CharGroup::INVLD,  // 126  ~
         CharGroup::INVLD,  // 127  DEL
     ];
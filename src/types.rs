@@ -0,0 +1,352 @@
+use std::fmt;
+
+/* The types of values a variable or expression can have. Structured types refer to their
+ * definition in the TypeTable, so two of them are the same type only if they come from the same
+ * definition. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Integer,
+    Boolean,
+    Char,
+    Real,
+    String,
+    Enum(usize),
+    Subrange(usize),
+    Set(usize),
+    Pointer(usize),
+    Array(usize),
+    Record(usize),
+}
+
+impl Type {
+    // Ordinal types have a first and last value, and each value has a successor
+    pub fn is_ordinal(self) -> bool {
+        matches!(self, Type::Integer | Type::Boolean | Type::Char | Type::Enum(_) | Type::Subrange(_))
+    }
+
+    // Simple types fit in a single word, so they can be returned from functions
+    pub fn is_simple(self) -> bool {
+        !matches!(self, Type::Set(_) | Type::Array(_) | Type::Record(_))
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Integer => write!(f, "integer"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::Char => write!(f, "char"),
+            Type::Real => write!(f, "real"),
+            Type::String => write!(f, "string"),
+            Type::Enum(_) => write!(f, "enumeration"),
+            Type::Subrange(_) => write!(f, "subrange"),
+            Type::Set(_) => write!(f, "set"),
+            Type::Pointer(_) => write!(f, "pointer"),
+            Type::Array(_) => write!(f, "array"),
+            Type::Record(_) => write!(f, "record"),
+        }
+    }
+}
+
+pub struct EnumDef {
+    pub name: Option<String>,
+    pub values: Vec<String>,
+}
+
+// A subrange holds the values of its base type from low to high, and is stored just like them
+pub struct SubrangeDef {
+    pub name: Option<String>,
+    pub base: Type,
+    pub low: i32,
+    pub high: i32,
+}
+
+// Sets are stored as bitsets of SET_SIZE bytes, so their elements must have ordinals from 0 to 255
+pub const SET_SIZE: u32 = 32;
+
+pub struct SetDef {
+    pub name: Option<String>,
+
+    // The type of the elements, which the empty set `[]` does not have
+    pub elem: Option<Type>,
+}
+
+pub struct PointerDef {
+    pub name: Option<String>,
+
+    // The type pointed to, which nil does not have. It is also missing while the type section
+    // that names it is still being parsed.
+    pub target: Option<Type>,
+}
+
+pub struct ArrayDef {
+    pub name: Option<String>,
+    pub index: Type,
+    pub low: i32,
+    pub high: i32,
+    pub elem: Type,
+}
+
+pub struct Field {
+    pub name: String,
+    pub dtype: Type,
+
+    // Where the field starts, in bytes from the start of the record
+    pub offset: u32,
+}
+
+pub struct RecordDef {
+    pub name: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+impl RecordDef {
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/* Definitions of the structured types declared by the program */
+pub struct TypeTable {
+    enums: Vec<EnumDef>,
+    subranges: Vec<SubrangeDef>,
+    sets: Vec<SetDef>,
+    pointers: Vec<PointerDef>,
+    arrays: Vec<ArrayDef>,
+    records: Vec<RecordDef>,
+}
+
+impl TypeTable {
+    pub fn new() -> TypeTable {
+        TypeTable { enums: vec![], subranges: vec![], sets: vec![], pointers: vec![], arrays: vec![], records: vec![] }
+    }
+
+    pub fn add_enum(&mut self, def: EnumDef) -> Type {
+        self.enums.push(def);
+        Type::Enum(self.enums.len() - 1)
+    }
+
+    pub fn enumeration(&self, id: usize) -> &EnumDef {
+        &self.enums[id]
+    }
+
+    pub fn add_subrange(&mut self, def: SubrangeDef) -> Type {
+        self.subranges.push(def);
+        Type::Subrange(self.subranges.len() - 1)
+    }
+
+    pub fn subrange(&self, id: usize) -> &SubrangeDef {
+        &self.subranges[id]
+    }
+
+    pub fn add_set(&mut self, def: SetDef) -> Type {
+        self.sets.push(def);
+        Type::Set(self.sets.len() - 1)
+    }
+
+    pub fn set(&self, id: usize) -> &SetDef {
+        &self.sets[id]
+    }
+
+    pub fn add_pointer(&mut self, def: PointerDef) -> Type {
+        self.pointers.push(def);
+        Type::Pointer(self.pointers.len() - 1)
+    }
+
+    pub fn pointer(&self, id: usize) -> &PointerDef {
+        &self.pointers[id]
+    }
+
+    pub fn set_target(&mut self, id: usize, target: Type) {
+        self.pointers[id].target = Some(target);
+    }
+
+    pub fn add_array(&mut self, def: ArrayDef) -> Type {
+        self.arrays.push(def);
+        Type::Array(self.arrays.len() - 1)
+    }
+
+    pub fn array(&self, id: usize) -> &ArrayDef {
+        &self.arrays[id]
+    }
+
+    pub fn add_record(&mut self, def: RecordDef) -> Type {
+        self.records.push(def);
+        Type::Record(self.records.len() - 1)
+    }
+
+    pub fn record(&self, id: usize) -> &RecordDef {
+        &self.records[id]
+    }
+
+    // Give a name to a type that does not have one yet, for error messages
+    pub fn set_name(&mut self, dtype: Type, name: &str) {
+        let def_name = match dtype {
+            Type::Enum(id) => &mut self.enums[id].name,
+            Type::Subrange(id) => &mut self.subranges[id].name,
+            Type::Set(id) => &mut self.sets[id].name,
+            Type::Pointer(id) => &mut self.pointers[id].name,
+            Type::Array(id) => &mut self.arrays[id].name,
+            Type::Record(id) => &mut self.records[id].name,
+            _ => return,
+        };
+        def_name.get_or_insert_with(|| name.to_string());
+    }
+
+    // Describe a type for error messages, by name where it has one
+    pub fn name(&self, dtype: Type) -> String {
+        match dtype {
+            Type::Enum(id) => {
+                let def = self.enumeration(id);
+                def.name.clone().unwrap_or_else(|| format!("({})", def.values.join(", ")))
+            },
+            Type::Subrange(id) => {
+                let def = self.subrange(id);
+                def.name.clone().unwrap_or_else(|| format!("subrange of {}", self.name(def.base)))
+            },
+            Type::Set(id) => {
+                let def = self.set(id);
+                match (&def.name, def.elem) {
+                    (Some(name), _) => name.clone(),
+                    (None, Some(elem)) => format!("set of {}", self.name(elem)),
+                    (None, None) => "empty set".to_string(),
+                }
+            },
+            Type::Pointer(id) => {
+                let def = self.pointer(id);
+                match (&def.name, def.target) {
+                    (Some(name), _) => name.clone(),
+                    (None, Some(target)) => format!("^{}", self.name(target)),
+                    (None, None) => "nil".to_string(),
+                }
+            },
+            Type::Array(id) => {
+                let def = self.array(id);
+                match &def.name {
+                    Some(name) => name.clone(),
+                    None => format!("array of {}", self.name(def.elem)),
+                }
+            },
+            Type::Record(id) => self.record(id).name.clone().unwrap_or_else(|| "record".to_string()),
+            _ => dtype.to_string(),
+        }
+    }
+
+    // The type a value of the given type takes on within expressions, where subranges act as
+    // their base type
+    pub fn base(&self, dtype: Type) -> Type {
+        match dtype {
+            Type::Subrange(id) => self.subrange(id).base,
+            _ => dtype,
+        }
+    }
+
+    // Whether a value of type `found` can be used where a value of type `expected` is wanted. Types
+    // have to be the same, except that sets only need elements of the same base type, pointers only
+    // need to point to the same type, and the empty set and nil go with any set or pointer.
+    pub fn compatible(&self, found: Type, expected: Type) -> bool {
+        match (found, expected) {
+            (Type::Set(a), Type::Set(b)) => match (self.set(a).elem, self.set(b).elem) {
+                (Some(a), Some(b)) => self.base(a) == self.base(b),
+                _ => true,
+            },
+            (Type::Pointer(a), Type::Pointer(b)) => match (self.pointer(a).target, self.pointer(b).target) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            },
+            _ => found == expected,
+        }
+    }
+
+    // The ordinals of the first and last values of an ordinal type
+    pub fn bounds(&self, dtype: Type) -> Option<(i32, i32)> {
+        match dtype {
+            Type::Integer => Some((i32::MIN, i32::MAX)),
+            Type::Boolean => Some((0, 1)),
+            Type::Char => Some((0, 255)),
+            Type::Enum(id) => Some((0, self.enumeration(id).values.len() as i32 - 1)),
+            Type::Subrange(id) => {
+                let def = self.subrange(id);
+                Some((def.low, def.high))
+            },
+            _ => None,
+        }
+    }
+
+    // The number of bytes taken up by an array with the given bounds and elements, or None when
+    // that does not fit in 32 bits
+    pub fn array_size(&self, low: i32, high: i32, elem: Type) -> Option<u32> {
+        let count = (high as i64 - low as i64 + 1) as u64;
+        count.checked_mul(self.size(elem) as u64).filter(|&size| size <= u32::MAX as u64).map(|size| size as u32)
+    }
+
+    // The number of bytes taken up by a value of the given type
+    pub fn size(&self, dtype: Type) -> u32 {
+        match dtype {
+            Type::Array(id) => {
+                let def = self.array(id);
+                self.array_size(def.low, def.high, def.elem).expect("Array size should have been checked")
+            },
+            Type::Record(id) => self.record(id).fields.iter().map(|f| self.size(f.dtype)).sum(),
+            Type::Set(_) => SET_SIZE,
+            _ => 4,
+        }
+    }
+}
+
+/* The value of a constant, known at compile time */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i32),
+    Boolean(bool),
+    Char(u8),
+    Real(f32),
+    Str(String),
+
+    // The position of a value within the enumeration with the given id
+    Enum(usize, i32),
+}
+
+impl Value {
+    pub fn dtype(&self) -> Type {
+        match self {
+            Value::Integer(_) => Type::Integer,
+            Value::Boolean(_) => Type::Boolean,
+            Value::Char(_) => Type::Char,
+            Value::Real(_) => Type::Real,
+            Value::Str(_) => Type::String,
+            Value::Enum(id, _) => Type::Enum(*id),
+        }
+    }
+
+    // The position of an ordinal value within its type, which is also how it is stored
+    pub fn ordinal(&self) -> Option<i32> {
+        match *self {
+            Value::Integer(v) => Some(v),
+            Value::Boolean(v) => Some(v as i32),
+            Value::Char(v) => Some(v as i32),
+            Value::Enum(_, v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // The value of an ordinal type at the given position
+    pub fn from_ordinal(dtype: Type, ordinal: i32) -> Option<Value> {
+        match dtype {
+            Type::Integer => Some(Value::Integer(ordinal)),
+            Type::Boolean => Some(Value::Boolean(ordinal != 0)),
+            Type::Char => Some(Value::Char(ordinal as u8)),
+            Type::Enum(id) => Some(Value::Enum(id, ordinal)),
+            _ => None,
+        }
+    }
+
+    // Numbers as reals, for arithmetic that mixes integers and reals
+    pub fn real(&self) -> Option<f32> {
+        match *self {
+            Value::Integer(v) => Some(v as f32),
+            Value::Real(v) => Some(v),
+            _ => None,
+        }
+    }
+}